@@ -0,0 +1,256 @@
+//! Reading and writing traces in the supported formats.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    error::Error,
+    ffi::OsStr,
+    fmt,
+    io::{self, BufRead, BufWriter, Write},
+    path::Path,
+};
+
+use forrust_fire_tracing::{html::write_html, stream::load_stream};
+use forrust_fire_tree::ashes::{Ashes, BranchId, serde::AshDeserStorage};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{Value, json};
+
+/// A trace loaded from any of the formats.
+#[derive(Debug, Default)]
+pub struct Trace {
+    /// The tree. Each payload is the `v` object of its node.
+    pub ashes: Ashes<Value>,
+    /// The `follows_from` key of the root node: the ID of each span, mapped to the IDs of
+    /// the spans it follows from.
+    pub follows_from: BTreeMap<u64, Vec<u64>>,
+    /// The `dropped` keys of the nodes, by node ID (`None` for the root node). Nodes which
+    /// nothing was dropped from are left out.
+    pub dropped: HashMap<Option<u64>, u64>,
+}
+
+impl Trace {
+    /// Creates a trace out of a part of this one, keeping the follows-from links between the
+    /// nodes of `ashes` and their dropped counts. The dropped count of the root node is only
+    /// kept if `keep_root` is set.
+    pub fn part(&self, ashes: Ashes<Value>, keep_root: bool) -> Self {
+        let ids = collect_ids(&ashes);
+        let follows_from = self
+            .follows_from
+            .iter()
+            .filter(|(id, _)| ids.contains(id))
+            .map(|(&id, follows)| {
+                let follows = follows.iter().copied().filter(|id| ids.contains(id));
+                (id, follows.collect::<Vec<_>>())
+            })
+            .filter(|(_, follows)| !follows.is_empty())
+            .collect();
+        let dropped = self
+            .dropped
+            .iter()
+            .filter(|(id, _)| match id {
+                Some(id) => ids.contains(id),
+                None => keep_root,
+            })
+            .map(|(&id, &dropped)| (id, dropped))
+            .collect();
+        Self {
+            ashes,
+            follows_from,
+            dropped,
+        }
+    }
+
+    /// The number of nodes dropped from the node with the given ID (from the root node for
+    /// `None`).
+    pub fn dropped_under(&self, id: Option<u64>) -> u64 {
+        self.dropped.get(&id).copied().unwrap_or(0)
+    }
+
+    /// Whether the trace has any follows-from links or dropped counts.
+    pub fn has_links_or_drops(&self) -> bool {
+        !self.follows_from.is_empty() || !self.dropped.is_empty()
+    }
+}
+
+fn collect_ids(ashes: &Ashes<Value>) -> HashSet<u64> {
+    let mut ids = HashSet::new();
+    let mut stack = vec![BranchId::ROOT];
+    while let Some(branch) = stack.pop() {
+        for child in ashes.branch(branch).child_iter() {
+            ids.extend(ashes.branch(child).payload().and_then(node_id));
+            stack.push(child);
+        }
+    }
+    ids
+}
+
+fn node_id(v: &Value) -> Option<u64> {
+    v["id"].as_u64()
+}
+
+impl Serialize for Trace {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        SerializeTrace {
+            tree: self.ashes.serializable_with_ctx(|ctx| SerializeNode {
+                v: ctx.payload,
+                dropped: node_id(ctx.payload).map_or(0, |id| self.dropped_under(Some(id))),
+            }),
+            follows_from: &self.follows_from,
+            dropped: self.dropped_under(None),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[derive(Serialize)]
+struct SerializeTrace<'a, Tree> {
+    #[serde(flatten)]
+    tree: Tree,
+    #[serde(skip_serializing_if = "is_empty")]
+    follows_from: &'a BTreeMap<u64, Vec<u64>>,
+    #[serde(skip_serializing_if = "is_zero")]
+    dropped: u64,
+}
+
+#[derive(Serialize)]
+struct SerializeNode<'a> {
+    v: &'a Value,
+    #[serde(skip_serializing_if = "is_zero")]
+    dropped: u64,
+}
+
+fn is_empty(map: &&BTreeMap<u64, Vec<u64>>) -> bool {
+    map.is_empty()
+}
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// The whole tree as one JSON object, as opened by fireWatch.
+    Json,
+    /// A node stream, one JSON object per node (see `forrust_fire_tracing::stream`).
+    Jsonl,
+    /// A standalone HTML report. Can only be written.
+    Html,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(Self::Json),
+            "jsonl" => Some(Self::Jsonl),
+            "html" => Some(Self::Html),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format of a file from its extension, defaulting to JSON.
+    pub fn of_path(path: &Path) -> Self {
+        path.extension()
+            .and_then(OsStr::to_str)
+            .and_then(Self::parse)
+            .unwrap_or(Self::Json)
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Json => "json",
+            Self::Jsonl => "jsonl",
+            Self::Html => "html",
+        })
+    }
+}
+
+pub fn load(reader: impl BufRead, format: Format) -> Result<Trace, Box<dyn Error>> {
+    match format {
+        Format::Json => {
+            // read into a `Value` first, since `AshDeserStorage` skips over the keys which
+            // aren't part of the tree
+            let mut deserializer = serde_json::Deserializer::from_reader(reader);
+            // traces are nested as deeply as the spans they were recorded from
+            deserializer.disable_recursion_limit();
+            let value = Value::deserialize(&mut deserializer)?;
+            deserializer.end()?;
+            let mut storage = AshDeserStorage::new();
+            storage.deser(&value)?;
+            let mut trace = Trace {
+                ashes: storage.ashes,
+                ..Trace::default()
+            };
+            if let Some(follows_from) = value.get("follows_from") {
+                trace.follows_from = BTreeMap::deserialize(follows_from)?;
+            }
+            load_dropped(&mut trace, &value)?;
+            Ok(trace)
+        }
+        Format::Jsonl => Ok(Trace {
+            ashes: load_stream(reader)?,
+            ..Trace::default()
+        }),
+        Format::Html => Err("HTML reports can't be read back".into()),
+    }
+}
+
+/// Reads the `dropped` keys of every node, where `root` was deserialized into the trace.
+fn load_dropped(trace: &mut Trace, root: &Value) -> Result<(), Box<dyn Error>> {
+    let mut stack = vec![(root, BranchId::ROOT)];
+    while let Some((node, branch)) = stack.pop() {
+        if let Some(dropped) = node.get("dropped") {
+            let id = trace.ashes.branch(branch).payload().map(node_id);
+            if id == Some(None) {
+                return Err("node with a `dropped` key has no `id`".into());
+            }
+            trace
+                .dropped
+                .insert(id.flatten(), u64::deserialize(dropped)?);
+        }
+        // children are deserialized in the order of their keys
+        let children = trace.ashes.branch(branch).child_iter().enumerate();
+        stack.extend(children.map(|(i, child)| (&node[i.to_string()], child)));
+    }
+    Ok(())
+}
+
+pub fn save(trace: &Trace, writer: impl Write, format: Format) -> io::Result<()> {
+    let mut writer = BufWriter::new(writer);
+    match format {
+        Format::Json => serde_json::to_writer(&mut writer, trace)?,
+        Format::Jsonl => write_stream(&trace.ashes, &mut writer)?,
+        Format::Html => write_html(trace, &mut writer)?,
+    }
+    writer.flush()
+}
+
+/// Writes every node as a node stream line, in pre-order. Nodes are numbered in the order
+/// they are written, so parents always come first.
+///
+/// Node streams have no place for follows-from links and dropped counts, so they are left
+/// out.
+fn write_stream(trace: &Ashes<Value>, writer: &mut impl Write) -> io::Result<()> {
+    let mut next_id = 0;
+    let mut stack = vec![(BranchId::ROOT, None)];
+    while let Some((branch, parent)) = stack.pop() {
+        let branch = trace.branch(branch);
+        let id = if branch.is_root() {
+            None
+        } else {
+            let id = next_id;
+            next_id += 1;
+            let v = branch.payload();
+            serde_json::to_writer(&mut *writer, &json!({ "id": id, "parent": parent, "v": v }))?;
+            writeln!(writer)?;
+            Some(id)
+        };
+        // the stack is popped from the back, so children are pushed in reverse
+        let children: Vec<_> = branch.child_iter().collect();
+        stack.extend(children.into_iter().rev().map(|child| (child, id)));
+    }
+    Ok(())
+}
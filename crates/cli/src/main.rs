@@ -0,0 +1,210 @@
+//! A command line tool for inspecting traces produced by
+//! [`forrust_fire_tracing`](https://crates.io/crates/forrust_fire_tracing).
+//!
+//! Traces can be printed, filtered, summarized, cut down to a subtree, and converted between
+//! the JSON tree (as opened by fireWatch), node stream and HTML report formats. Run `fire help`
+//! for the details.
+//!
+//! This crate is part of the [`forrust_fire`](https://github.com/purple-ic/forrust_fire) collection.
+
+use std::{
+    env,
+    error::Error,
+    fmt::Display,
+    fs::File,
+    io::{self, BufReader, ErrorKind, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use crate::{
+    format::{Format, Trace},
+    select::{Filter, NodeRef},
+    stats::Stats,
+};
+
+mod format;
+mod print;
+mod select;
+mod stats;
+#[cfg(test)]
+mod test;
+
+const USAGE: &str = "\
+Inspects traces produced by forrust_fire_tracing.
+
+Usage: fire <command> <trace> [options]
+
+Commands:
+  print <trace>              Prints the tree
+  filter <trace> <filter>... Keeps the nodes matching every filter, along with their parents
+  stats <trace>              Shows node counts per level and target, depth and fan-out
+  subtree <trace> <node>     Extracts a node and its descendants. The node is given either
+                             as the path of child indices leading to it (like `0.2.1`) or
+                             as `#<id>`
+  convert <trace> <output>   Converts a trace into another format
+  help                       Prints this message
+
+Filters:
+  --level <level>            Nodes at this level or a more severe one
+  --target <target>          Nodes whose target is this module or inside of it
+  --field <name>[=<value>]   Nodes with this field, optionally set to this value
+
+Options:
+  -o, --output <file>        Writes the result of `filter` or `subtree` into a file instead
+                             of printing it
+  --from <format>            The format of the trace
+  --to <format>              The format of the output
+
+The formats are `json` (the tree, as opened by fireWatch), `jsonl` (a node stream, which
+leaves out follows-from links and dropped node counts) and `html` (a standalone report,
+which can't be read back). By default, they are guessed from file extensions.
+";
+
+#[derive(Debug, Default)]
+struct Args {
+    command: String,
+    positional: Vec<String>,
+    filters: Vec<Filter>,
+    output: Option<PathBuf>,
+    from: Option<Format>,
+    to: Option<Format>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut out = Self {
+            command: args.next().ok_or("missing command")?,
+            ..Self::default()
+        };
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for `{arg}`"))
+            };
+            let parse_format = |format: String| {
+                Format::parse(&format).ok_or_else(|| format!("unknown format `{format}`"))
+            };
+            match arg.as_str() {
+                "--level" => {
+                    let level = value()?;
+                    let filter =
+                        Filter::level(&level).ok_or_else(|| format!("unknown level `{level}`"))?;
+                    out.filters.push(filter);
+                }
+                "--target" => out.filters.push(Filter::Target(value()?)),
+                "--field" => out.filters.push(Filter::field(&value()?)),
+                "-o" | "--output" => out.output = Some(value()?.into()),
+                "--from" => out.from = Some(parse_format(value()?)?),
+                "--to" => out.to = Some(parse_format(value()?)?),
+                _ if arg.starts_with('-') => {
+                    return Err(format!("unknown option `{arg}`"));
+                }
+                _ => out.positional.push(arg),
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) if matches!(args.command.as_str(), "help" | "-h" | "--help") => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Ok(args) => args,
+        Err(err) => {
+            eprint!("error: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let command = args.command.as_str();
+    if !args.filters.is_empty() && command != "filter" {
+        return Err(format!("filters can't be used with `{command}`").into());
+    }
+    if args.output.is_some() && !matches!(command, "filter" | "subtree") {
+        return Err(format!("`--output` can't be used with `{command}`").into());
+    }
+    let [input, rest @ ..] = args.positional.as_slice() else {
+        return Err("missing trace file".into());
+    };
+    let trace = load(Path::new(input), args.from)?;
+
+    match (command, rest) {
+        ("print", []) => print(print::print_tree(&trace))?,
+        ("stats", []) => print(Stats::of(&trace))?,
+        ("filter", []) => {
+            if args.filters.is_empty() {
+                return Err("missing filters".into());
+            }
+            output(&select::filter(&trace, &args.filters), args)?;
+        }
+        ("subtree", [node]) => {
+            let node = NodeRef::parse(node).ok_or_else(|| format!("invalid node `{node}`"))?;
+            let branch = node.find(&trace).ok_or_else(|| format!("no node {node}"))?;
+            output(&select::subtree(&trace, branch), args)?;
+        }
+        ("convert", [path]) => save(&trace, Path::new(path), args.to)?,
+        ("print" | "stats" | "filter" | "subtree" | "convert", _) => {
+            return Err(format!("wrong number of arguments for `{command}`").into());
+        }
+        _ => return Err(format!("unknown command `{command}`").into()),
+    }
+    Ok(())
+}
+
+fn load(path: &Path, format: Option<Format>) -> Result<Trace, Box<dyn Error>> {
+    let format = format.unwrap_or_else(|| Format::of_path(path));
+    let file =
+        File::open(path).map_err(|err| format!("could not open {}: {err}", path.display()))?;
+    format::load(BufReader::new(file), format)
+        .map_err(|err| format!("could not load {} as {format}: {err}", path.display()).into())
+}
+
+fn save(trace: &Trace, path: &Path, format: Option<Format>) -> Result<(), Box<dyn Error>> {
+    let format = format.unwrap_or_else(|| Format::of_path(path));
+    File::create(path)
+        .and_then(|file| write(trace, file, format))
+        .map_err(|err| format!("could not write {}: {err}", path.display()).into())
+}
+
+/// Writes a trace in `format`, warning about the parts of it which the format can't hold.
+fn write(trace: &Trace, writer: impl Write, format: Format) -> io::Result<()> {
+    if format == Format::Jsonl && trace.has_links_or_drops() {
+        eprintln!(
+            "warning: node streams can't hold follows-from links or dropped node counts, so \
+             they are left out"
+        );
+    }
+    format::save(trace, writer, format)
+}
+
+/// Writes a trace to `--output`, or prints it if there is none.
+fn output(trace: &Trace, args: &Args) -> Result<(), Box<dyn Error>> {
+    match &args.output {
+        Some(path) => save(trace, path, args.to),
+        None => match args.to {
+            Some(format) => Ok(write(trace, io::stdout().lock(), format)?),
+            None => Ok(print(print::print_tree(trace))?),
+        },
+    }
+}
+
+/// Prints to stdout, stopping quietly if it was closed (for example, by `head`).
+fn print(value: impl Display) -> io::Result<()> {
+    match write!(io::stdout().lock(), "{value}") {
+        Err(err) if err.kind() == ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
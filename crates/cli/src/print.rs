@@ -0,0 +1,62 @@
+//! Printing traces for humans.
+
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::{format::Trace, select::field_str};
+
+/// Prints every node on its own line, indented by its depth. Messages spanning multiple
+/// lines are indented a bit further.
+pub fn print_tree(trace: &Trace) -> impl fmt::Display + '_ {
+    trace.ashes.print_tree(|f, v, depth| {
+        let Some(v) = v else {
+            return writeln!(f, "<root>");
+        };
+        let mut node = String::new();
+        write_node(&mut node, v)?;
+        let indent = "  ".repeat(depth);
+        for (i, line) in node.lines().enumerate() {
+            let extra = if i == 0 { "" } else { "  " };
+            writeln!(f, "{indent}{extra}{line}")?;
+        }
+        Ok(())
+    })
+}
+
+/// Writes a node like `INFO message {field=value, ...}`. Spans are shown by their name and
+/// events by their message. Fields which were never recorded are left out.
+fn write_node(f: &mut impl fmt::Write, v: &Value) -> fmt::Result {
+    if let Some(level) = v["level"].as_str() {
+        write!(f, "{level} ")?;
+    }
+    let ctx = v["ctx"].as_object();
+    let message = match v["is_span"].as_bool() {
+        Some(false) => ctx.and_then(|ctx| ctx.get("message")?.as_str()),
+        _ => None,
+    };
+    match (message, v["name"].as_str()) {
+        (Some(message), _) => f.write_str(message)?,
+        (None, Some(name)) => f.write_str(name)?,
+        (None, None) => f.write_str("?")?,
+    }
+
+    let fields = ctx
+        .into_iter()
+        .flatten()
+        .filter(|(name, value)| !value.is_null() && (message.is_none() || *name != "message"));
+    let mut any = false;
+    for (name, value) in fields {
+        f.write_str(if any { ", " } else { " {" })?;
+        write!(f, "{name}={}", field_str(value))?;
+        any = true;
+    }
+    if any {
+        f.write_str("}")?;
+    }
+
+    if v["unwound"] == true {
+        f.write_str(" (unwound)")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,206 @@
+//! Selecting parts of a trace.
+
+use std::{borrow::Cow, collections::HashSet, fmt};
+
+use forrust_fire_tree::{
+    ashes::{Ashes, BranchId},
+    fire::{self, ForestFire},
+};
+use serde_json::Value;
+
+use crate::format::Trace;
+
+/// The levels of `tracing`, from least to most severe.
+pub const LEVELS: [&str; 5] = ["TRACE", "DEBUG", "INFO", "WARN", "ERROR"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    /// Nodes at this level (an index into [`LEVELS`]) or a more severe one.
+    Level(usize),
+    /// Nodes whose target is this module or inside of it.
+    Target(String),
+    /// Nodes with this field, optionally set to this value.
+    Field { name: String, value: Option<String> },
+}
+
+impl Filter {
+    pub fn level(level: &str) -> Option<Self> {
+        LEVELS
+            .iter()
+            .position(|l| l.eq_ignore_ascii_case(level))
+            .map(Self::Level)
+    }
+
+    pub fn field(field: &str) -> Self {
+        match field.split_once('=') {
+            Some((name, value)) => Self::Field {
+                name: name.to_owned(),
+                value: Some(value.to_owned()),
+            },
+            None => Self::Field {
+                name: field.to_owned(),
+                value: None,
+            },
+        }
+    }
+
+    pub fn matches(&self, v: &Value) -> bool {
+        match self {
+            Self::Level(min) => v["level"]
+                .as_str()
+                .and_then(|level| LEVELS.iter().position(|l| *l == level))
+                .is_some_and(|level| level >= *min),
+            Self::Target(target) => v["target"].as_str().is_some_and(|t| {
+                t.strip_prefix(target.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            }),
+            Self::Field { name, value } => match v["ctx"].get(name) {
+                Some(field) => value
+                    .as_ref()
+                    .is_none_or(|value| field_str(field) == *value),
+                None => false,
+            },
+        }
+    }
+}
+
+/// Formats a field value the way fireWatch shows it.
+pub fn field_str(value: &Value) -> Cow<'_, str> {
+    match value {
+        Value::String(str) => Cow::Borrowed(str),
+        // error chains
+        Value::Array(values) if !values.is_empty() && values.iter().all(Value::is_string) => {
+            let strs: Vec<_> = values.iter().filter_map(Value::as_str).collect();
+            Cow::Owned(strs.join(": "))
+        }
+        _ => Cow::Owned(value.to_string()),
+    }
+}
+
+/// Keeps the nodes matching every filter, along with their ancestors.
+pub fn filter(trace: &Trace, filters: &[Filter]) -> Trace {
+    // every node along with its parent, with parents coming before their children
+    let mut edges = Vec::new();
+    let mut stack = vec![BranchId::ROOT];
+    while let Some(branch) = stack.pop() {
+        for child in trace.ashes.branch(branch).child_iter() {
+            edges.push((branch, child));
+            stack.push(child);
+        }
+    }
+
+    let mut kept = HashSet::new();
+    // backwards, so that each node's descendants are marked before the node itself
+    for &(parent, child) in edges.iter().rev() {
+        let matches = trace
+            .ashes
+            .branch(child)
+            .payload()
+            .is_some_and(|v| filters.iter().all(|filter| filter.matches(v)));
+        if matches || kept.contains(&child) {
+            kept.insert(parent);
+            kept.insert(child);
+        }
+    }
+    let mut fire = ForestFire::new();
+    copy_children(
+        &trace.ashes,
+        BranchId::ROOT,
+        &mut fire,
+        fire::BranchId::ROOT,
+        &|branch| kept.contains(&branch),
+    );
+    trace.part(fire.burn(), true)
+}
+
+/// A node given on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeRef {
+    /// The child indices leading to the node from root, like the keys of the JSON format.
+    Path(Vec<usize>),
+    /// The node's `id`.
+    Id(u64),
+}
+
+impl NodeRef {
+    pub fn parse(node: &str) -> Option<Self> {
+        match node.strip_prefix('#') {
+            Some(id) => id.parse().ok().map(Self::Id),
+            None => node
+                .split('.')
+                .map(|idx| idx.parse().ok())
+                .collect::<Option<_>>()
+                .map(Self::Path),
+        }
+    }
+
+    pub fn find(&self, trace: &Trace) -> Option<BranchId> {
+        match self {
+            Self::Path(path) => path.iter().try_fold(BranchId::ROOT, |branch, &idx| {
+                let branch = trace.ashes.branch(branch);
+                (idx < branch.n_children()).then(|| branch.child(idx))
+            }),
+            Self::Id(id) => find_id(trace, *id),
+        }
+    }
+}
+
+/// Returns the first node with the given `id`, in pre-order.
+fn find_id(trace: &Trace, id: u64) -> Option<BranchId> {
+    let mut stack = vec![BranchId::ROOT];
+    while let Some(branch) = stack.pop() {
+        let node = trace.ashes.branch(branch);
+        if node.payload().is_some_and(|v| v["id"] == id) {
+            return Some(branch);
+        }
+        // the stack is popped from the back, so children are pushed in reverse
+        let children: Vec<_> = node.child_iter().collect();
+        stack.extend(children.into_iter().rev());
+    }
+    None
+}
+
+impl fmt::Display for NodeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Path(path) => {
+                for (i, idx) in path.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(".")?;
+                    }
+                    write!(f, "{idx}")?;
+                }
+                Ok(())
+            }
+            Self::Id(id) => write!(f, "#{id}"),
+        }
+    }
+}
+
+/// Returns a trace containing only `branch` and its descendants.
+pub fn subtree(trace: &Trace, branch: BranchId) -> Trace {
+    let mut fire = ForestFire::new();
+    let parent = match trace.ashes.branch(branch).payload() {
+        Some(payload) => fire.branch(fire::BranchId::ROOT, payload.clone()),
+        None => fire::BranchId::ROOT,
+    };
+    copy_children(&trace.ashes, branch, &mut fire, parent, &|_| true);
+    trace.part(fire.burn(), branch.is_root())
+}
+
+fn copy_children(
+    ashes: &Ashes<Value>,
+    from: BranchId,
+    fire: &mut ForestFire<Value>,
+    to: fire::BranchId,
+    keep: &impl Fn(BranchId) -> bool,
+) {
+    let mut stack = vec![(from, to)];
+    while let Some((from, to)) = stack.pop() {
+        // all children of a node are added at once, so they keep their order
+        for child in ashes.branch(from).child_iter().filter(|&child| keep(child)) {
+            let payload = ashes.branch(child).payload().cloned().unwrap_or_default();
+            stack.push((child, fire.branch(to, payload)));
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Statistics about the shape and contents of a trace.
+
+use std::{collections::HashMap, fmt};
+
+use forrust_fire_tree::ashes::BranchId;
+
+use crate::{format::Trace, select::LEVELS};
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub nodes: usize,
+    pub spans: usize,
+    pub events: usize,
+    /// The depth of the deepest node. Children of root have a depth of `1`.
+    pub max_depth: usize,
+    /// The most children any node has, not counting root.
+    pub max_fan_out: usize,
+    /// How many nodes (again, not counting root) have any children.
+    pub parents: usize,
+    pub root_children: usize,
+    pub levels: HashMap<String, usize>,
+    pub targets: HashMap<String, usize>,
+}
+
+impl Stats {
+    pub fn of(trace: &Trace) -> Self {
+        let mut stats = Self {
+            root_children: trace.ashes.root().n_children(),
+            ..Self::default()
+        };
+        let mut stack = vec![(BranchId::ROOT, 0)];
+        while let Some((branch, depth)) = stack.pop() {
+            stats.visit(trace, branch, depth);
+            let children = trace.ashes.branch(branch).child_iter();
+            stack.extend(children.map(|child| (child, depth + 1)));
+        }
+        stats
+    }
+
+    fn visit(&mut self, trace: &Trace, branch: BranchId, depth: usize) {
+        let branch = trace.ashes.branch(branch);
+        if let Some(v) = branch.payload() {
+            self.nodes += 1;
+            match v["is_span"].as_bool() {
+                Some(true) => self.spans += 1,
+                Some(false) => self.events += 1,
+                None => {}
+            }
+            self.max_depth = self.max_depth.max(depth);
+            if branch.n_children() != 0 {
+                self.max_fan_out = self.max_fan_out.max(branch.n_children());
+                self.parents += 1;
+            }
+            for (key, counts) in [("level", &mut self.levels), ("target", &mut self.targets)] {
+                if let Some(value) = v[key].as_str() {
+                    *counts.entry(value.to_owned()).or_default() += 1;
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "nodes: {} ({} spans, {} events)",
+            self.nodes, self.spans, self.events
+        )?;
+        writeln!(f, "max depth: {}", self.max_depth)?;
+        if self.parents != 0 {
+            // every node other than the children of root has a parent which isn't root
+            let children = self.nodes - self.root_children;
+            writeln!(
+                f,
+                "fan-out: at most {}, {:.1} on average for nodes with children",
+                self.max_fan_out,
+                children as f64 / self.parents as f64
+            )?;
+        }
+
+        // most severe first
+        let mut levels: Vec<_> = self.levels.iter().collect();
+        levels.sort_by_key(|(level, _)| std::cmp::Reverse(LEVELS.iter().position(|l| l == level)));
+        write_counts(f, "levels", &levels)?;
+
+        let mut targets: Vec<_> = self.targets.iter().collect();
+        targets.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        write_counts(f, "targets", &targets)
+    }
+}
+
+fn write_counts(f: &mut fmt::Formatter, title: &str, counts: &[(&String, &usize)]) -> fmt::Result {
+    if counts.is_empty() {
+        return Ok(());
+    }
+    writeln!(f, "\n{title}:")?;
+    let width = counts.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, count) in counts {
+        writeln!(f, "  {name:width$}  {count}")?;
+    }
+    Ok(())
+}
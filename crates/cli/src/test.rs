@@ -0,0 +1,292 @@
+use serde_json::{Value, json};
+
+use crate::{
+    Args,
+    format::{self, Format, Trace},
+    print::print_tree,
+    select::{self, Filter, NodeRef},
+    stats::Stats,
+};
+
+fn trace() -> Trace {
+    let json = json!({
+        "0": {
+            "v": {
+                "id": 0, "name": "server", "target": "app::server", "level": "INFO",
+                "is_span": true, "ctx": { "port": 80 }
+            },
+            "0": {
+                "v": {
+                    "id": 1, "name": "event a", "target": "app::server", "level": "DEBUG",
+                    "is_span": false, "ctx": { "message": "listening" }
+                }
+            },
+            "1": {
+                "v": {
+                    "id": 2, "name": "request", "target": "app::server_http", "level": "INFO",
+                    "is_span": true, "unwound": true, "ctx": { "path": "/" }
+                },
+                "0": {
+                    "v": {
+                        "id": 3, "name": "event b", "target": "app::server_http",
+                        "level": "ERROR", "is_span": false,
+                        "ctx": { "message": "failed", "error": ["bad request", "eof"] }
+                    }
+                }
+            }
+        },
+        "1": {
+            "v": {
+                "id": 4, "name": "event c", "target": "app", "level": "WARN",
+                "is_span": false, "ctx": { "message": "shutting down", "code": 3 }
+            }
+        }
+    });
+    format::load(json.to_string().as_bytes(), Format::Json).unwrap()
+}
+
+fn names(trace: &Trace) -> Vec<String> {
+    print_tree(trace)
+        .to_string()
+        .lines()
+        .map(str::to_owned)
+        .collect()
+}
+
+#[test]
+fn print() {
+    assert_eq!(
+        names(&trace()),
+        [
+            "<root>",
+            "  INFO server {port=80}",
+            "    DEBUG listening",
+            "    INFO request {path=/} (unwound)",
+            "      ERROR failed {error=bad request: eof}",
+            "  WARN shutting down {code=3}",
+        ]
+    );
+
+    // events don't need a message, and fields may have been left empty
+    let json = json!({
+        "0": {
+            "v": {
+                "name": "event a", "level": "INFO", "is_span": false,
+                "ctx": { "two_plus_two": 4, "empty": null }
+            }
+        }
+    });
+    let trace = format::load(json.to_string().as_bytes(), Format::Json).unwrap();
+    assert_eq!(names(&trace), ["<root>", "  INFO event a {two_plus_two=4}"]);
+}
+
+#[test]
+fn filters() {
+    let trace = trace();
+    let filtered = |filters: &[Filter]| names(&select::filter(&trace, filters));
+
+    assert_eq!(
+        filtered(&[Filter::level("warn").unwrap()]),
+        [
+            "<root>",
+            "  INFO server {port=80}",
+            "    INFO request {path=/} (unwound)",
+            "      ERROR failed {error=bad request: eof}",
+            "  WARN shutting down {code=3}",
+        ]
+    );
+    // `app::server_http` isn't inside of `app::server`
+    assert_eq!(
+        filtered(&[Filter::Target("app::server".into())]),
+        ["<root>", "  INFO server {port=80}", "    DEBUG listening"]
+    );
+    assert_eq!(
+        filtered(&[Filter::field("code=3")]),
+        ["<root>", "  WARN shutting down {code=3}"]
+    );
+    assert_eq!(
+        filtered(&[Filter::field("error"), Filter::Target("app".into())]),
+        [
+            "<root>",
+            "  INFO server {port=80}",
+            "    INFO request {path=/} (unwound)",
+            "      ERROR failed {error=bad request: eof}",
+        ]
+    );
+    assert_eq!(filtered(&[Filter::field("code=4")]), ["<root>"]);
+}
+
+#[test]
+fn subtrees() {
+    let trace = trace();
+    let subtree = |node: &str| {
+        let node = NodeRef::parse(node).unwrap();
+        node.find(&trace)
+            .map(|branch| names(&select::subtree(&trace, branch)))
+    };
+
+    let request = [
+        "<root>",
+        "  INFO request {path=/} (unwound)",
+        "    ERROR failed {error=bad request: eof}",
+    ];
+    assert_eq!(subtree("0.1").unwrap(), request);
+    assert_eq!(subtree("#2").unwrap(), request);
+    assert_eq!(
+        subtree("1").unwrap(),
+        ["<root>", "  WARN shutting down {code=3}"]
+    );
+    assert_eq!(subtree("0.2"), None);
+    assert_eq!(subtree("#5"), None);
+    assert_eq!(NodeRef::parse("0.x"), None);
+    assert_eq!(NodeRef::parse("#"), None);
+}
+
+#[test]
+fn stats() {
+    let stats = Stats::of(&trace());
+    assert_eq!(stats.nodes, 5);
+    assert_eq!(stats.spans, 2);
+    assert_eq!(stats.events, 3);
+    assert_eq!(stats.max_depth, 3);
+    assert_eq!(stats.max_fan_out, 2);
+    assert_eq!(stats.parents, 2);
+    assert_eq!(stats.levels["INFO"], 2);
+    assert_eq!(stats.targets["app::server_http"], 2);
+
+    let text = stats.to_string();
+    assert!(
+        text.contains("fan-out: at most 2, 1.5 on average"),
+        "{text}"
+    );
+    let levels: Vec<_> = text
+        .lines()
+        .skip_while(|line| *line != "levels:")
+        .skip(1)
+        .take_while(|line| !line.is_empty())
+        .collect();
+    assert_eq!(
+        levels,
+        ["  ERROR  1", "  WARN   1", "  INFO   2", "  DEBUG  1"]
+    );
+}
+
+#[test]
+fn formats() {
+    let trace = trace();
+    let expected = serde_json::to_value(&trace).unwrap();
+
+    let mut stream = Vec::new();
+    format::save(&trace, &mut stream, Format::Jsonl).unwrap();
+    let first: Value =
+        serde_json::from_slice(stream.split(|&b| b == b'\n').next().unwrap()).unwrap();
+    assert_eq!(first["parent"], Value::Null);
+    assert_eq!(first["v"]["name"], "server");
+    let loaded = format::load(&stream[..], Format::Jsonl).unwrap();
+    assert_eq!(serde_json::to_value(&loaded).unwrap(), expected);
+
+    let mut html = Vec::new();
+    format::save(&trace, &mut html, Format::Html).unwrap();
+    assert!(
+        String::from_utf8(html)
+            .unwrap()
+            .contains(r#""message":"shutting down""#)
+    );
+    assert!(format::load(&b""[..], Format::Html).is_err());
+
+    assert_eq!(Format::of_path("trace.jsonl".as_ref()), Format::Jsonl);
+    assert_eq!(Format::of_path("report.html".as_ref()), Format::Html);
+    assert_eq!(Format::of_path("trace".as_ref()), Format::Json);
+}
+
+#[test]
+fn links_and_drops() {
+    let node = |id: u64, target: &str| json!({ "id": id, "target": target });
+    let json = json!({
+        "0": {
+            "v": node(0, "app::a"),
+            "dropped": 2,
+            "0": { "v": node(1, "app::a") }
+        },
+        "1": { "v": node(2, "app::b"), "dropped": 1 },
+        "follows_from": { "1": [0], "2": [0] },
+        "dropped": 5
+    });
+    let trace = format::load(json.to_string().as_bytes(), Format::Json).unwrap();
+    assert_eq!(trace.dropped_under(None), 5);
+    assert_eq!(trace.dropped_under(Some(0)), 2);
+    assert_eq!(trace.dropped_under(Some(1)), 0);
+    let mut saved = Vec::new();
+    format::save(&trace, &mut saved, Format::Json).unwrap();
+    assert_eq!(serde_json::from_slice::<Value>(&saved).unwrap(), json);
+
+    let mut html = Vec::new();
+    format::save(&trace, &mut html, Format::Html).unwrap();
+    let html = String::from_utf8(html).unwrap();
+    assert!(html.contains(r#""follows_from":{"1":[0],"2":[0]},"dropped":5"#));
+
+    // only the links between the kept nodes, and the counts of the kept nodes, are kept
+    let filtered = select::filter(&trace, &[Filter::Target("app::b".to_owned())]);
+    assert_eq!(
+        serde_json::to_value(&filtered).unwrap(),
+        json!({ "0": { "v": node(2, "app::b"), "dropped": 1 }, "dropped": 5 })
+    );
+    let subtree = select::subtree(&trace, NodeRef::Id(0).find(&trace).unwrap());
+    assert_eq!(
+        serde_json::to_value(&subtree).unwrap(),
+        json!({
+            "0": { "v": node(0, "app::a"), "dropped": 2, "0": { "v": node(1, "app::a") } },
+            "follows_from": { "1": [0] }
+        })
+    );
+
+    let no_id = json!({ "0": { "v": {}, "dropped": 1 } });
+    assert!(format::load(no_id.to_string().as_bytes(), Format::Json).is_err());
+}
+
+#[test]
+fn deep_traces() {
+    const DEPTH: u64 = 300;
+
+    // deeper than the default recursion limit of serde_json
+    let mut json = r#"{"0":"#.to_owned();
+    for id in 0..DEPTH {
+        let level = if id == DEPTH - 1 { "ERROR" } else { "INFO" };
+        json += &format!(r#"{{"v":{{"id":{id},"level":"{level}"}},"dropped":1,"0":"#);
+    }
+    json += &json!({ "v": { "id": DEPTH } }).to_string();
+    json += &"}".repeat(DEPTH as usize + 1);
+    let trace = format::load(json.as_bytes(), Format::Json).unwrap();
+    assert_eq!(Stats::of(&trace).max_depth, DEPTH as usize + 1);
+    assert_eq!(trace.dropped_under(Some(DEPTH - 1)), 1);
+
+    let deepest = NodeRef::Id(DEPTH).find(&trace).unwrap();
+    assert_eq!(Stats::of(&select::subtree(&trace, deepest)).nodes, 1);
+    let filtered = select::filter(&trace, &[Filter::level("error").unwrap()]);
+    assert_eq!(Stats::of(&filtered).nodes, DEPTH as usize);
+
+    let mut stream = Vec::new();
+    format::save(&trace, &mut stream, Format::Jsonl).unwrap();
+    let loaded = format::load(&stream[..], Format::Jsonl).unwrap();
+    assert_eq!(Stats::of(&loaded).max_depth, DEPTH as usize + 1);
+}
+
+#[test]
+fn args() {
+    let parse = |args: &str| Args::parse(args.split(' ').map(str::to_owned));
+
+    let args = parse("filter trace.json --level info --field a=b -o out.jsonl --to json").unwrap();
+    assert_eq!(args.command, "filter");
+    assert_eq!(args.positional, ["trace.json"]);
+    assert_eq!(
+        args.filters,
+        [Filter::level("INFO").unwrap(), Filter::field("a=b")]
+    );
+    assert_eq!(args.output.as_deref(), Some("out.jsonl".as_ref()));
+    assert_eq!(args.to, Some(Format::Json));
+
+    assert!(parse("filter trace.json --level loud").is_err());
+    assert!(parse("convert a.json b.xml --to xml").is_err());
+    assert!(parse("print trace.json --target").is_err());
+    assert!(parse("print trace.json --verbose").is_err());
+}
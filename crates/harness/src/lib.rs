@@ -0,0 +1,192 @@
+//! A test harness which records tests into a tree and exports a report of _how_ they failed.
+//!
+//! Tests marked with [`#[forrust_fire::test]`](test) are run just like regular `#[test]`s,
+//! except that they are traced by a [`ForestFireSubscriber`] using the [`LogEventProvider`].
+//! If the test passes, the tree is thrown away; if it fails, the tree is written as JSON
+//! which can be opened with [fireWatch](https://purple-ic.github.io/forrust_fire/fireWatch/).
+//!
+//! ```
+//! #[forrust_fire::test]
+//! fn adds_up() {
+//!     let _span = tracing::info_span!("adding").entered();
+//!     tracing::info!(two_plus_two = 2 + 2);
+//!     assert_eq!(2 + 2, 4);
+//! }
+//! ```
+//!
+//! Reports are written to the [`report_dir`], with one file per failed test named after its
+//! path (for example, `my_crate.tests.adds_up.json`). A report left over from an earlier run
+//! is removed once the test passes.
+//!
+//! A test counts as failed if it panics, or if it returns an `Err`. Like with `#[test]`, the
+//! `#[should_panic]` and `#[ignore]` attributes can be added below `#[forrust_fire::test]`;
+//! `#[should_panic]` tests fail if they _don't_ panic, or if the panic message doesn't contain
+//! the `expected` string.
+//!
+//! Panics are recorded into the tree using a [`PanicHook`], which is installed the first
+//! time a test is run. Only the thread running the test is traced; threads spawned by the
+//! test need to [set the default subscriber] themselves.
+//!
+//! [`ForestFireSubscriber`]: forrust_fire_tracing::ForestFireSubscriber
+//! [`LogEventProvider`]: forrust_fire_tracing::providers::log::LogEventProvider
+//! [`PanicHook`]: forrust_fire_tracing::panic::PanicHook
+//! [set the default subscriber]: https://docs.rs/tracing/latest/tracing/dispatcher/index.html
+//!
+//! This crate is part of the [`forrust_fire`](https://github.com/purple-ic/forrust_fire) collection.
+
+// lets `#[forrust_fire::test]` be used within this crate
+extern crate self as forrust_fire;
+
+use std::{
+    any::Any,
+    env,
+    fs::{self, File},
+    io::{self, BufWriter, ErrorKind, Write},
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::Once,
+};
+
+use forrust_fire_tracing::{
+    panic::PanicHook,
+    providers::{
+        ProviderExt,
+        log::{LogAshes, LogEventProvider},
+    },
+};
+
+pub use forrust_fire_macros::test;
+
+#[cfg(test)]
+mod test;
+
+/// The environment variable which overrides the [`report_dir`].
+pub const REPORT_DIR_VAR: &str = "FORRUST_FIRE_REPORT_DIR";
+
+/// Returns the directory which reports of failed tests are written to.
+///
+/// This is the value of the [`REPORT_DIR_VAR`] environment variable if it is set, or
+/// `forrust_fire` inside of cargo's target directory otherwise.
+pub fn report_dir() -> PathBuf {
+    match env::var_os(REPORT_DIR_VAR) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => target_dir().join("forrust_fire"),
+    }
+}
+
+fn target_dir() -> PathBuf {
+    // test binaries are placed somewhere inside of the target directory, which cargo marks
+    // with a `CACHEDIR.TAG` file
+    env::current_exe()
+        .ok()
+        .and_then(|exe| {
+            exe.ancestors()
+                .skip(1)
+                .find(|dir| dir.join("CACHEDIR.TAG").is_file())
+                .map(Path::to_path_buf)
+        })
+        .unwrap_or_else(|| PathBuf::from("target"))
+}
+
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::{ShouldPanic, TestResult, run_test};
+}
+
+/// The `#[should_panic]` attribute of a test.
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShouldPanic {
+    No,
+    Yes,
+    /// The panic message must contain this string.
+    YesWithMessage(&'static str),
+}
+
+impl ShouldPanic {
+    /// Whether a panic with the given payload is the expected one, the same way libtest
+    /// decides it.
+    fn matches(self, payload: &(dyn Any + Send)) -> bool {
+        let expected = match self {
+            Self::No => return false,
+            Self::Yes => return true,
+            Self::YesWithMessage(expected) => expected,
+        };
+        let message = payload
+            .downcast_ref::<String>()
+            .map(String::as_str)
+            .or_else(|| payload.downcast_ref::<&str>().copied());
+        message.is_some_and(|message| message.contains(expected))
+    }
+}
+
+/// The return types allowed for tests.
+#[doc(hidden)]
+pub trait TestResult {
+    fn is_failure(&self) -> bool;
+}
+
+impl TestResult for () {
+    fn is_failure(&self) -> bool {
+        false
+    }
+}
+
+impl<T, E> TestResult for Result<T, E> {
+    fn is_failure(&self) -> bool {
+        self.is_err()
+    }
+}
+
+#[doc(hidden)]
+pub fn run_test<T: TestResult>(
+    name: &str,
+    should_panic: ShouldPanic,
+    test: impl FnOnce() -> T,
+) -> T {
+    run_test_in(&report_dir(), name, should_panic, test)
+}
+
+fn run_test_in<T: TestResult>(
+    dir: &Path,
+    name: &str,
+    should_panic: ShouldPanic,
+    test: impl FnOnce() -> T,
+) -> T {
+    static PANIC_HOOK: Once = Once::new();
+    PANIC_HOOK.call_once(|| PanicHook::new().install());
+
+    let (result, log_ashes) =
+        LogEventProvider::new().nothread_run_ret(|| panic::catch_unwind(AssertUnwindSafe(test)));
+    let failed = match &result {
+        Ok(out) => should_panic != ShouldPanic::No || out.is_failure(),
+        Err(payload) => !should_panic.matches(&**payload),
+    };
+
+    let path = dir.join(format!("{}.json", name.replace("::", ".")));
+    if failed {
+        match write_report(&path, &log_ashes) {
+            Ok(()) => eprintln!("forrust_fire: wrote a report to {}", path.display()),
+            Err(err) => eprintln!("forrust_fire: could not write {}: {err}", path.display()),
+        }
+    } else if let Err(err) = fs::remove_file(&path)
+        && err.kind() != ErrorKind::NotFound
+    {
+        eprintln!("forrust_fire: could not remove {}: {err}", path.display());
+    }
+    drop(log_ashes);
+
+    match result {
+        Ok(out) => out,
+        Err(payload) => panic::resume_unwind(payload),
+    }
+}
+
+fn write_report(path: &Path, log_ashes: &LogAshes) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(&mut writer, log_ashes)?;
+    writer.flush()
+}
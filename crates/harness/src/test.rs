@@ -0,0 +1,113 @@
+use std::{
+    env, fs,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    process,
+};
+
+use serde_json::Value;
+use tracing::{info, info_span};
+
+use crate::{ShouldPanic, run_test_in};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("forrust_fire_{}_{name}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn read_report(dir: &Path, file: &str) -> Value {
+    serde_json::from_slice(&fs::read(dir.join(file)).unwrap()).unwrap()
+}
+
+#[forrust_fire::test]
+fn passes() {
+    let _span = info_span!("adding").entered();
+    info!(two_plus_two = 2 + 2);
+}
+
+#[forrust_fire::test]
+#[should_panic(expected = "as expected")]
+fn panics_as_expected() {
+    panic!("panicked as expected");
+}
+
+#[forrust_fire::test]
+#[should_panic = "expected"]
+fn panics_with_message() {
+    panic!("as expected");
+}
+
+#[forrust_fire::test]
+fn returns_ok() -> Result<(), String> {
+    let four: u32 = "4".parse().map_err(|_| "not a number")?;
+    assert_eq!(four, 4);
+    Ok(())
+}
+
+#[test]
+fn panic_report() {
+    let dir = temp_dir("panic_report");
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        run_test_in::<()>(&dir, "tests::failing", ShouldPanic::No, || {
+            let _span = info_span!("setup").entered();
+            info!("preparing");
+            panic!("oh no");
+        })
+    }));
+    assert!(result.is_err());
+
+    let report = read_report(&dir, "tests.failing.json");
+    let span = &report["0"];
+    assert_eq!(span["v"]["name"], "setup");
+    assert_eq!(span["v"]["unwound"], true);
+    assert_eq!(span["0"]["v"]["ctx"]["message"], "preparing");
+    assert_eq!(span["1"]["v"]["target"], "panic");
+    assert_eq!(span["1"]["v"]["ctx"]["message"], "oh no");
+
+    // passing removes the old report
+    run_test_in(&dir, "tests::failing", ShouldPanic::No, || ());
+    assert!(!dir.join("tests.failing.json").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn outcomes() {
+    let dir = temp_dir("outcomes");
+
+    run_test_in(&dir, "ok", ShouldPanic::No, || info!("fine"));
+    let ok: Result<(), ()> = run_test_in(&dir, "ok_result", ShouldPanic::No, || Ok(()));
+    assert!(ok.is_ok());
+    let result = panic::catch_unwind(|| {
+        run_test_in::<()>(&dir, "expected", ShouldPanic::Yes, || panic!("expected"))
+    });
+    assert!(result.is_err());
+    // like with libtest, the panic message has to contain the expected string
+    let expected = ShouldPanic::YesWithMessage("expected");
+    let result = panic::catch_unwind(|| {
+        run_test_in::<()>(&dir, "expected_message", expected, || panic!("as expected"))
+    });
+    assert!(result.is_err());
+    assert!(!dir.exists());
+
+    let err: Result<(), &str> = run_test_in(&dir, "err", ShouldPanic::No, || {
+        info!("about to fail");
+        Err("failed")
+    });
+    assert_eq!(err, Err("failed"));
+    assert_eq!(
+        read_report(&dir, "err.json")["0"]["v"]["ctx"]["message"],
+        "about to fail"
+    );
+
+    run_test_in(&dir, "did_not_panic", ShouldPanic::Yes, || ());
+    assert!(dir.join("did_not_panic.json").is_file());
+    let fails = |name: &str, test: fn()| {
+        let result = panic::catch_unwind(|| run_test_in(&dir, name, expected, test));
+        assert!(result.is_err());
+        assert!(dir.join(format!("{name}.json")).is_file(), "{name}");
+    };
+    fails("wrong_message", || panic!("something else"));
+    fails("not_a_string", || panic::panic_any(4));
+    fs::remove_dir_all(&dir).unwrap();
+}
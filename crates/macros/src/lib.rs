@@ -0,0 +1,96 @@
+//! Procedural macros for [`forrust_fire`](https://crates.io/crates/forrust_fire).
+//!
+//! You shouldn't need to depend on this crate directly; its macros are re-exported by
+//! `forrust_fire`.
+//!
+//! This crate is part of the [`forrust_fire`](https://github.com/purple-ic/forrust_fire) collection.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{
+    Attribute, Error, Expr, ExprLit, ItemFn, Lit, LitStr, Meta, ReturnType, parse_macro_input,
+    spanned::Spanned,
+};
+
+/// Marks a function as a test which is traced into a tree, writing a report if it fails.
+///
+/// See the `forrust_fire` crate for details.
+#[proc_macro_attribute]
+pub fn test(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemFn);
+    if !attr.is_empty() {
+        let attr = proc_macro2::TokenStream::from(attr);
+        return Error::new(attr.span(), "`#[forrust_fire::test]` takes no arguments")
+            .into_compile_error()
+            .into();
+    }
+    if let Some(asyncness) = item.sig.asyncness {
+        return Error::new(asyncness.span, "async tests are not supported")
+            .into_compile_error()
+            .into();
+    }
+
+    // with `#[should_panic]`, the test fails when it *doesn't* panic
+    let should_panic = match item
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("should_panic"))
+        .map(should_panic)
+    {
+        None => quote!(No),
+        Some(Ok(None)) => quote!(Yes),
+        Some(Ok(Some(expected))) => quote!(YesWithMessage(#expected)),
+        Some(Err(err)) => return err.into_compile_error().into(),
+    };
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = item;
+    let name = &sig.ident;
+    let output = match &sig.output {
+        ReturnType::Default => quote!(()),
+        ReturnType::Type(_, ty) => quote!(#ty),
+    };
+
+    quote! {
+        #[::core::prelude::v1::test]
+        #(#attrs)*
+        #vis #sig {
+            ::forrust_fire::__private::run_test(
+                ::core::concat!(::core::module_path!(), "::", ::core::stringify!(#name)),
+                ::forrust_fire::__private::ShouldPanic::#should_panic,
+                move || -> #output #block,
+            )
+        }
+    }
+    .into()
+}
+
+/// Parses the `expected` message out of `#[should_panic]`, `#[should_panic = "..."]` or
+/// `#[should_panic(expected = "...")]`.
+fn should_panic(attr: &Attribute) -> syn::Result<Option<LitStr>> {
+    match &attr.meta {
+        Meta::Path(_) => Ok(None),
+        Meta::NameValue(meta) => match &meta.value {
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(expected),
+                ..
+            }) => Ok(Some(expected.clone())),
+            value => Err(Error::new(value.span(), "expected a string literal")),
+        },
+        Meta::List(_) => {
+            let mut expected = None;
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("expected") {
+                    expected = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `expected = \"...\"`"))
+                }
+            })?;
+            Ok(expected)
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Measures how many events per second `ForestFireSubscriber` records when several threads
+//! log at once.
+//!
+//! Every configuration is run twice with `LogEventProvider`: once as it comes, recording
+//! every event under the subscriber's lock, and once [with buffering], recording events into
+//! per-thread buffers.
+//!
+//! Run with `cargo bench -p forrust_fire_tracing`.
+//!
+//! [with buffering]: LogEventProvider::with_buffering
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use forrust_fire_tracing::{providers::log::LogEventProvider, run_forest};
+
+const EVENTS: usize = 400_000;
+const RUNS: usize = 5;
+
+/// Logs `EVENTS` events spread over `threads` threads, returning how long it took.
+fn run(provider: LogEventProvider, threads: usize) -> Duration {
+    let per_thread = EVENTS / threads;
+    let start = Instant::now();
+    let ash_trayce = run_forest(provider, |scope| {
+        for t in 0..threads {
+            scope.spawn(move |_| {
+                let _span = tracing::info_span!("worker", t).entered();
+                for i in 0..per_thread {
+                    tracing::info!(i, "working");
+                }
+            });
+        }
+    });
+    let elapsed = start.elapsed();
+    assert_eq!(ash_trayce.ash.root().n_children(), threads);
+    black_box(ash_trayce);
+    elapsed
+}
+
+fn bench(name: &str, provider: fn() -> LogEventProvider, threads: usize) {
+    let best = (0..RUNS).map(|_| run(provider(), threads)).min().unwrap();
+    let per_sec = EVENTS as f64 / best.as_secs_f64();
+    println!(
+        "{name:>10}, {threads:>2} threads: {:>6.2}M events/s",
+        per_sec / 1e6
+    );
+}
+
+fn main() {
+    for threads in [1, 4, 16] {
+        bench("unbuffered", LogEventProvider::new, threads);
+        bench(
+            "buffered",
+            || LogEventProvider::new().with_buffering(),
+            threads,
+        );
+    }
+}
@@ -0,0 +1,449 @@
+function nn(value) {
+  if (value == null || value == void 0) {
+    throw `value is ${value}`;
+  } else {
+    return value;
+  }
+}
+function intervalWhenShown(callback, interval) {
+  let manager = null;
+  function update2() {
+    if (document.hidden) {
+      if (manager != null) {
+        console.debug("removing interval");
+        clearInterval(manager);
+        manager = null;
+      }
+    } else {
+      if (manager == null) {
+        console.debug("setting interval");
+        manager = setInterval(callback, interval);
+      }
+    }
+  }
+  document.addEventListener("visibilitychange", () => {
+    update2();
+  });
+  update2();
+}
+function elById(ty, id) {
+  const el = document.getElementById(id);
+  if (el == null) {
+    throw `element ${id} not found`;
+  } else if (el instanceof ty) {
+    return el;
+  } else {
+    throw `element ${id} does not have expected type`;
+  }
+}
+function removeFromParent(node) {
+  node.parentNode?.removeChild(node);
+}
+function buildChild(parent, name, func) {
+  const element = document.createElement(name);
+  if (func != void 0)
+    func(element);
+  parent.appendChild(element);
+  return element;
+}
+let progress = 0;
+const title = elById(HTMLHeadingElement, "title");
+const str = nn(title.textContent);
+title.textContent = "";
+[...str].forEach((char) => {
+  const element = document.createElement("span");
+  element.textContent = char;
+  element.classList.add("fire-char");
+  title.appendChild(element);
+});
+const els = title.childElementCount;
+function setState(idx, orange, red) {
+  const RNG = "fire-char-1";
+  const RED = "fire-char-2";
+  const cl = title.childNodes[idx % els].classList;
+  if (orange) {
+    cl.add(RNG);
+  } else {
+    cl.remove(RNG);
+  }
+  if (red) {
+    cl.add(RED);
+  } else {
+    cl.remove(RED);
+  }
+}
+function update() {
+  if (!title.checkVisibility()) {
+    return;
+  }
+  setState(progress, false, false);
+  progress += 1;
+  progress %= els;
+  setState(progress, true, false);
+  setState(progress + 1, false, true);
+  setState(progress + 2, true, false);
+}
+intervalWhenShown(update, 100);
+const demo = {
+  "0": {
+    "v": {
+      "ctx": {
+        "message": "woa"
+      },
+      "file": "crates/tracing/src/test/tracing1.rs",
+      "is_span": false,
+      "level": "INFO",
+      "line": 14,
+      "mod": "forrust_fire_tracing::test::tracing1",
+      "name": "event crates/tracing/src/test/tracing1.rs:14",
+      "target": "forrust_fire_tracing::test::tracing1"
+    }
+  },
+  "1": {
+    "0": {
+      "v": {
+        "ctx": {
+          "message": "yaaa"
+        },
+        "file": "crates/tracing/src/test/tracing1.rs",
+        "is_span": false,
+        "level": "TRACE",
+        "line": 17,
+        "mod": "forrust_fire_tracing::test::tracing1",
+        "name": "event crates/tracing/src/test/tracing1.rs:17",
+        "target": "forrust_fire_tracing::test::tracing1"
+      }
+    },
+    "1": {
+      "0": {
+        "v": {
+          "ctx": {
+            "message": "hello world!"
+          },
+          "file": "crates/tracing/src/test/tracing1.rs",
+          "is_span": false,
+          "level": "DEBUG",
+          "line": 10,
+          "mod": "forrust_fire_tracing::test::tracing1",
+          "name": "event crates/tracing/src/test/tracing1.rs:10",
+          "target": "forrust_fire_tracing::test::tracing1"
+        }
+      },
+      "1": {
+        "v": {
+          "ctx": {
+            "message": "hello world, but in an evil manner"
+          },
+          "file": "crates/tracing/src/test/tracing1.rs",
+          "is_span": false,
+          "level": "ERROR",
+          "line": 11,
+          "mod": "forrust_fire_tracing::test::tracing1",
+          "name": "event crates/tracing/src/test/tracing1.rs:11",
+          "target": "forrust_fire_tracing::test::tracing1"
+        }
+      },
+      "v": {
+        "ctx": {},
+        "file": "crates/tracing/src/test/tracing1.rs",
+        "is_span": true,
+        "level": "INFO",
+        "line": 8,
+        "mod": "forrust_fire_tracing::test::tracing1",
+        "name": "funkabloid",
+        "target": "forrust_fire_tracing::test::tracing1"
+      }
+    },
+    "v": {
+      "ctx": {
+        "two_plus_two": "4"
+      },
+      "file": "crates/tracing/src/test/tracing1.rs",
+      "is_span": true,
+      "level": "WARN",
+      "line": 16,
+      "mod": "forrust_fire_tracing::test::tracing1",
+      "name": "hello!",
+      "target": "forrust_fire_tracing::test::tracing1"
+    }
+  }
+};
+class TreeStats {
+  nodes = 0;
+  htmlNodes = 0;
+  buildEl(name, func) {
+    this.htmlNodes++;
+    const el = document.createElement(name);
+    if (func != void 0)
+      func(el);
+    return el;
+  }
+  buildChild(parent, name, func) {
+    this.htmlNodes++;
+    return buildChild(parent, name, func);
+  }
+}
+function formatNanos(nanos) {
+  if (nanos < 1e3)
+    return `${nanos}ns`;
+  if (nanos < 1e6)
+    return `${(nanos / 1e3).toFixed(1)}µs`;
+  if (nanos < 1e9)
+    return `${(nanos / 1e6).toFixed(1)}ms`;
+  return `${(nanos / 1e9).toFixed(2)}s`;
+}
+class Links {
+  names = /* @__PURE__ */ new Map();
+  followsFrom = {};
+  constructor(tree) {
+    if (tree.follows_from != void 0)
+      this.followsFrom = tree.follows_from;
+    const stack = [tree];
+    let next;
+    while ((next = stack.pop()) != void 0) {
+      if (next.v?.id != void 0)
+        this.names.set(next.v.id, String(next.v.name));
+      for (const [key, child] of Object.entries(next))
+        if (!Number.isNaN(Number.parseInt(key, 10)))
+          stack.push(child);
+    }
+  }
+  describe(id) {
+    const name = this.names.get(id);
+    return name == void 0 ? `#${id}` : `${name} (#${id})`;
+  }
+}
+function prepareNodeMap(payload, links) {
+  const kvs = [];
+  if (payload == void 0)
+    return kvs;
+  if (payload.file != void 0 && payload.line != void 0)
+    kvs.push({
+      name: "at",
+      special: true,
+      value: `${payload.file}:${payload.line}`
+    });
+  const thread = payload.thread;
+  if (thread != void 0)
+    kvs.push({
+      name: "thread",
+      special: true,
+      value: thread.name == null ? `#${thread.id}` : `${thread.name} (#${thread.id})`
+    });
+  const time = payload.time;
+  if (time != void 0 && typeof time.busy == "number" && typeof time.idle == "number")
+    kvs.push({
+      name: "time",
+      special: true,
+      value: `busy ${formatNanos(time.busy)}, idle ${formatNanos(time.idle)}` + (typeof time.entries == "number" ? `, entered ${time.entries}x` : "")
+    });
+  const follows = payload.id == void 0 ? void 0 : links.followsFrom[String(payload.id)];
+  if (Array.isArray(follows))
+    kvs.push({
+      name: "follows from",
+      special: true,
+      value: follows.map((id) => links.describe(id)).join(", ")
+    });
+  const ctx = payload.ctx;
+  if (ctx != void 0)
+    for (const [key, value] of Object.entries(ctx)) {
+      if (payload.is_span === false && key == "message")
+        continue;
+      kvs.push({
+        name: key,
+        value: formatField(value),
+        special: false
+      });
+    }
+  return kvs;
+}
+function formatField(value) {
+  if (typeof value == "string")
+    return value;
+  if (Array.isArray(value) && value.length > 0 && value.every((v) => typeof v == "string"))
+    return value.join(": ");
+  return JSON.stringify(value);
+}
+function buildHeader(parent, stats, innerElement, payload, isMapEmpty) {
+  stats.buildChild(parent, "span", (span) => {
+    span.classList.add("payload-toggle-collapsed");
+    if (isMapEmpty) {
+      span.classList.add("payload-toggle-collapsed-empty");
+      return;
+    }
+    span.classList.add("payload-toggle-collapsed-interactive");
+    span.textContent = "[+]";
+    let collapsed = true;
+    span.addEventListener("click", () => {
+      if (collapsed) {
+        innerElement.classList.remove("tree-node-inner-collapsed");
+        span.textContent = "[-]";
+      } else {
+        innerElement.classList.add("tree-node-inner-collapsed");
+        span.textContent = "[+]";
+      }
+      collapsed = !collapsed;
+    });
+  });
+  if (payload.level != void 0) {
+    const level = String(payload.level);
+    const levelLo = level.toLowerCase();
+    stats.buildChild(parent, "span", (el) => {
+      el.textContent = level + " ";
+      el.classList.add("payload-level");
+      el.classList.add("payload-level-" + levelLo);
+    });
+  }
+  if (payload.is_span === false && payload.target === "panic") {
+    stats.buildChild(parent, "span", (el) => {
+      el.textContent = "PANIC ";
+      el.classList.add("payload-panic");
+    });
+  }
+  if (payload.unwound === true) {
+    stats.buildChild(parent, "span", (el) => {
+      el.textContent = "UNWOUND ";
+      el.title = "a panic passed through this span";
+      el.classList.add("payload-unwound");
+    });
+  }
+  const putName = (name) => stats.buildChild(parent, "span", (el) => {
+    el.textContent = String(name);
+    el.classList.add("payload-name");
+  });
+  if (payload.is_span === false && payload.ctx?.message != null) {
+    putName(payload.ctx.message);
+  } else if (payload.name != void 0) {
+    putName(payload.name);
+  }
+}
+function buildNodeMap(parent, stats, map) {
+  const div = stats.buildEl("div");
+  div.classList.add("payload");
+  for (const entry of map) {
+    const pairEl = stats.buildEl("p");
+    pairEl.classList.add("payload-pair");
+    stats.buildChild(pairEl, "span", (key) => {
+      key.textContent = entry.name;
+      key.classList.add("payload-key");
+      if (entry.special)
+        key.classList.add("payload-key-special");
+    }).insertAdjacentText("afterend", ": ");
+    stats.buildChild(pairEl, "span", (val) => {
+      val.textContent = entry.value;
+      val.classList.add("payload-val");
+    });
+    div.appendChild(pairEl);
+  }
+  parent.appendChild(div);
+}
+function buildTree(parent, stats, links, tree, level) {
+  const map = prepareNodeMap(tree.v, links);
+  const payload = tree.v;
+  parent.classList.add("tree-node-" + level % 2);
+  let children = [];
+  const inner = stats.buildEl("div");
+  if (tree.omitted != null) {
+    stats.buildChild(parent, "p", (p) => {
+      p.classList.add("payload-info");
+      stats.buildChild(p, "span", (el) => {
+        el.textContent = `(${tree.omitted?.nodes} nodes omitted)`;
+        el.classList.add("payload-omitted");
+        if (Array.isArray(tree.omitted?.ids))
+          el.title = "ids: " + tree.omitted.ids.join(", ");
+      });
+    });
+  }
+  if (payload != null) {
+    stats.buildChild(parent, "p", (p) => {
+      p.classList.add("payload-info");
+      buildHeader(p, stats, inner, payload, map.length == 0);
+    });
+  }
+  parent.appendChild(inner);
+  inner.classList.add("tree-node-inner");
+  if (level != 0)
+    inner.classList.add("tree-node-inner-collapsed");
+  buildNodeMap(inner, stats, map);
+  if (typeof tree.dropped == "number") {
+    const dropped = tree.dropped;
+    stats.buildChild(inner, "p", (p) => {
+      p.classList.add("payload-info");
+      stats.buildChild(p, "span", (el) => {
+        el.textContent = `(${dropped} nodes dropped, over budget)`;
+        el.classList.add("payload-omitted");
+      });
+    });
+  }
+  for (const [key, v] of Object.entries(tree)) {
+    const iKey = Number.parseInt(key, 10);
+    if (Number.isNaN(iKey)) {
+      continue;
+    } else {
+      const value = v;
+      while (children.length <= iKey) {
+        children.push(null);
+      }
+      const child = stats.buildEl("div");
+      child.classList.add("tree-node");
+      buildTree(child, stats, links, value, level + 1);
+      children[iKey] = child;
+      stats.nodes += 1;
+    }
+  }
+  for (const child of children) {
+    if (child == null)
+      continue;
+    inner.appendChild(child);
+  }
+}
+function begin(on) {
+  console.time("build tree");
+  console.log(on);
+  elById(HTMLElement, "select-wrapper").style.display = "none";
+  removeFromParent(title);
+  elById(HTMLElement, "view").style.display = "block";
+  elById(HTMLElement, "view-header-right").appendChild(title);
+  const treeView = elById(HTMLDivElement, "tree-view");
+  for (const child of treeView.children) {
+    if (child.id != "tree-view-root")
+      removeFromParent(child);
+  }
+  const stats = new TreeStats();
+  buildTree(treeView, stats, new Links(on), on, 0);
+  console.timeEnd("build tree");
+  elById(HTMLElement, "view-header-msg").textContent = `tree nodes: ${stats.nodes}; html nodes: ${stats.htmlNodes}`;
+}
+function beginOnFile(file) {
+  file.text().then((text) => JSON.parse(text)).then((obj) => begin(obj));
+}
+function openFileDialog() {
+  const i = document.createElement("input");
+  i.type = "file";
+  i.accept = ".json";
+  i.addEventListener("change", () => {
+    if (i.files == null) return;
+    const file = i.files[0];
+    if (file == void 0) return;
+    beginOnFile(file);
+  });
+  i.click();
+}
+window.addEventListener("drop", (e) => {
+  const dataTransfer = nn(e.dataTransfer);
+  for (const file of dataTransfer.files) {
+    e.preventDefault();
+    beginOnFile(file);
+    break;
+  }
+});
+window.addEventListener("dragover", (e) => {
+  e.preventDefault();
+});
+window.openFile = openFileDialog;
+window.openDemo = () => {
+  begin(demo);
+};
+const embedded = document.getElementById("embedded-trace");
+if (embedded?.textContent)
+  begin(JSON.parse(embedded.textContent));
@@ -0,0 +1,255 @@
+//! Limiting how much is recorded.
+//!
+//! A long-running program can record far more than fits into memory. With
+//! [`ForestFireSubscriber::with_budget`], a [`ForestFireSubscriber`] stops growing the tree
+//! once a [`Budget`] runs out, and keeps only what its [`OverflowPolicy`] allows from then on:
+//!
+//! ```
+//! use forrust_fire_tracing::{
+//!     ForestFireSubscriber,
+//!     budget::{Budget, OverflowPolicy},
+//!     nothread_run_subscriber_ret,
+//!     providers::log::LogEventProvider,
+//! };
+//! use forrust_fire_tree::fire::ForestFire;
+//!
+//! let subscriber = ForestFireSubscriber::new(ForestFire::new(), LogEventProvider::new())
+//!     .with_budget(Budget::new(OverflowPolicy::KeepSevere).max_nodes(10));
+//! let ((), ash_trayce) = nothread_run_subscriber_ret(subscriber, || {
+//!     for i in 0..100 {
+//!         tracing::info!(i, "chatty");
+//!     }
+//!     tracing::error!("kept anyway");
+//! });
+//!
+//! assert_eq!(ash_trayce.ash.root().n_children(), 11);
+//! assert_eq!(ash_trayce.dropped.at_root(), 90);
+//! ```
+//!
+//! Everything which is dropped is counted in [`DroppedNodes`], under the closest recorded
+//! ancestor of where it would have been placed.
+//!
+//! Instead of keeping the oldest part of the tree, a [`RingBuffer`] keeps the newest: it
+//! evicts the oldest root-level subtrees once they are complete.
+//!
+//! [`ForestFireSubscriber`]: crate::ForestFireSubscriber
+//! [`ForestFireSubscriber::with_budget`]: crate::ForestFireSubscriber::with_budget
+
+use std::collections::BTreeMap;
+
+use tracing::Level;
+
+/// Limits on how much a [`ForestFireSubscriber`] records, and what happens past them.
+///
+/// The budget runs out once either limit is reached. Without any limits, it never does.
+///
+/// [`ForestFireSubscriber`]: crate::ForestFireSubscriber
+#[derive(Debug, Clone)]
+pub struct Budget {
+    max_nodes: Option<usize>,
+    max_bytes: Option<usize>,
+    policy: OverflowPolicy,
+}
+
+impl Budget {
+    /// Creates a new budget without any limits, applying `policy` once a limit is reached.
+    pub const fn new(policy: OverflowPolicy) -> Self {
+        Self {
+            max_nodes: None,
+            max_bytes: None,
+            policy,
+        }
+    }
+
+    /// Limits the number of nodes in the tree.
+    pub const fn max_nodes(mut self, max_nodes: usize) -> Self {
+        self.max_nodes = Some(max_nodes);
+        self
+    }
+
+    /// Limits the number of bytes held by the provider, as reported by
+    /// [`EventProvider::used_bytes`].
+    ///
+    /// Since the size of a node is only known after it has been created, the last node
+    /// may go over this limit.
+    ///
+    /// [`EventProvider::used_bytes`]: crate::EventProvider::used_bytes
+    pub const fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Returns what happens once the budget has run out.
+    pub const fn policy(&self) -> OverflowPolicy {
+        self.policy
+    }
+
+    /// Returns whether the budget has run out with the given usage.
+    pub fn exceeded(&self, nodes: usize, bytes: usize) -> bool {
+        self.max_nodes.is_some_and(|max| nodes >= max)
+            || self.max_bytes.is_some_and(|max| bytes >= max)
+    }
+
+    /// Returns whether a new node is still recorded after the budget has run out.
+    ///
+    /// `at_root` is whether the node would be placed at root level, and `in_dropped`
+    /// whether it would be placed into a span which was dropped.
+    pub(crate) fn keeps(&self, level: Level, at_root: bool, in_dropped: bool) -> bool {
+        match self.policy {
+            OverflowPolicy::Stop => false,
+            OverflowPolicy::KeepSevere => level <= Level::WARN,
+            OverflowPolicy::DropSubtrees => !at_root && !in_dropped,
+        }
+    }
+}
+
+/// What a [`ForestFireSubscriber`] records once its [`Budget`] has run out.
+///
+/// [`ForestFireSubscriber`]: crate::ForestFireSubscriber
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Nothing new is recorded.
+    Stop,
+    /// Only `ERROR` and `WARN` spans and events are recorded. Those inside of a dropped span
+    /// are placed into its closest recorded ancestor instead.
+    ///
+    /// These are recorded regardless of the limits, so the tree can still grow without
+    /// bounds if they are common.
+    KeepSevere,
+    /// New root-level spans and events are dropped, along with everything inside of them.
+    /// Spans which were already recorded keep growing, so that every recorded subtree is
+    /// complete.
+    ///
+    /// The limits are only enforced at root level, so a long-lived root-level span can
+    /// still grow without bounds.
+    DropSubtrees,
+}
+
+/// How many nodes were dropped because a [`Budget`] ran out, by where they were lost.
+///
+/// Nodes are counted under the closest recorded ancestor of where they would have been
+/// placed, which is identified by its ID as given to the [`EventProvider`]. Nodes inside
+/// of a dropped span are counted as well.
+///
+/// [`EventProvider`]: crate::EventProvider
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DroppedNodes {
+    root: usize,
+    spans: BTreeMap<usize, usize>,
+}
+
+impl DroppedNodes {
+    pub(crate) const fn new() -> Self {
+        Self {
+            root: 0,
+            spans: BTreeMap::new(),
+        }
+    }
+
+    pub(crate) fn forget(&mut self, id: usize) {
+        self.spans.remove(&id);
+    }
+
+    pub(crate) fn add(&mut self, parent: Option<usize>) {
+        match parent {
+            Some(id) => *self.spans.entry(id).or_default() += 1,
+            None => self.root += 1,
+        }
+    }
+
+    /// Returns the total number of dropped nodes.
+    pub fn total(&self) -> usize {
+        self.root + self.spans.values().sum::<usize>()
+    }
+
+    /// Returns the number of nodes dropped outside of any recorded span.
+    pub fn at_root(&self) -> usize {
+        self.root
+    }
+
+    /// Returns the number of nodes dropped inside of the span with the given ID (but not
+    /// inside of any of its recorded children).
+    pub fn under(&self, id: usize) -> usize {
+        self.spans.get(&id).copied().unwrap_or(0)
+    }
+
+    /// Returns the IDs of spans under which nodes were dropped, along with how many.
+    pub fn spans(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.spans.iter().map(|(&id, &count)| (id, count))
+    }
+}
+
+/// Keeps only the most recent complete root-level subtrees, evicting older ones.
+///
+/// A root-level subtree is complete once its root-level span is closed, or right away for
+/// root-level events. Once more than the allowed subtrees are complete, the oldest ones are
+/// removed from the tree altogether (see [`ForestFire::remove_subtrees`]), and their memory
+/// is reclaimed through [`EventProvider::nodes_removed`]. Subtrees which aren't complete yet
+/// are never evicted, and still count towards the byte limit.
+///
+/// If both limits are set, subtrees are evicted until both are met. Anything recorded into
+/// an evicted subtree later on (such as into a span which outlived its root-level span) is
+/// discarded, and nodes [dropped](DroppedNodes) inside of it are no longer counted.
+///
+/// ```
+/// use forrust_fire_tracing::{
+///     ForestFireSubscriber, budget::RingBuffer, nothread_run_subscriber_ret,
+///     providers::log::LogEventProvider,
+/// };
+/// use forrust_fire_tree::fire::ForestFire;
+///
+/// let subscriber = ForestFireSubscriber::new(ForestFire::new(), LogEventProvider::new())
+///     .with_ring_buffer(RingBuffer::subtrees(2));
+/// let ((), ash_trayce) = nothread_run_subscriber_ret(subscriber, || {
+///     for i in 0..10 {
+///         tracing::info_span!("iteration", i).in_scope(|| tracing::info!("working"));
+///     }
+/// });
+///
+/// // only iterations 8 and 9 are left
+/// assert_eq!(ash_trayce.ash.root().n_children(), 2);
+/// ```
+///
+/// [`ForestFire::remove_subtrees`]: forrust_fire_tree::fire::ForestFire::remove_subtrees
+/// [`EventProvider::nodes_removed`]: crate::EventProvider::nodes_removed
+#[derive(Debug, Clone)]
+pub struct RingBuffer {
+    pub(crate) max_subtrees: Option<usize>,
+    pub(crate) max_bytes: Option<usize>,
+}
+
+impl RingBuffer {
+    /// Keeps at most `max` complete root-level subtrees.
+    pub const fn subtrees(max: usize) -> Self {
+        Self {
+            max_subtrees: Some(max),
+            max_bytes: None,
+        }
+    }
+
+    /// Keeps the newest complete root-level subtrees for which the provider's
+    /// [`used_bytes`] stay within `max`.
+    ///
+    /// [`used_bytes`]: crate::EventProvider::used_bytes
+    pub const fn bytes(max: usize) -> Self {
+        Self {
+            max_subtrees: None,
+            max_bytes: Some(max),
+        }
+    }
+
+    /// Additionally keeps at most `max` complete root-level subtrees.
+    pub const fn max_subtrees(mut self, max: usize) -> Self {
+        self.max_subtrees = Some(max);
+        self
+    }
+
+    /// Additionally limits the provider's [`used_bytes`] to `max`.
+    ///
+    /// [`used_bytes`]: crate::EventProvider::used_bytes
+    pub const fn max_bytes(mut self, max: usize) -> Self {
+        self.max_bytes = Some(max);
+        self
+    }
+}
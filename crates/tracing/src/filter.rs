@@ -0,0 +1,270 @@
+//! Deciding which spans and events are recorded.
+//!
+//! By default, a [`ForestFireSubscriber`] records everything it is given. With
+//! [`ForestFireSubscriber::with_filter`], it only records what a [`Filter`] enables.
+//! Filters are written like the `RUST_LOG` environment variable used by `env_logger` and
+//! `tracing-subscriber`: a comma-separated list of directives, each of which is one of:
+//!
+//! - `level`: enables everything at `level` and above (e.g. `warn` enables `WARN` and `ERROR`).
+//! - `target`: enables everything whose target starts with `target`.
+//! - `target=level`: enables everything at `level` and above whose target starts with `target`.
+//! - `target[span]=level` or `[span]=level`: enables everything at `level` and above while
+//!   inside a span named `span` (whose target starts with `target`, if given). The `=level`
+//!   part may be left out, which is the same as `=trace`.
+//!
+//! Levels are written as `off`, `error`, `warn`, `info`, `debug` or `trace` (case
+//! insensitive). Anything not enabled by any directive is left out.
+//!
+//! If several directives without a span match the same target, the one with the longest
+//! target wins, and out of those, the one which comes last. For example, `info,my_crate=trace,
+//! my_crate::noisy=off` records `INFO` and above everywhere, everything inside `my_crate`, and
+//! nothing inside `my_crate::noisy`. Span directives are applied on top of that: `warn,[request]=debug`
+//! records warnings everywhere, and also `DEBUG` and above inside of `request` spans.
+//!
+//! Callsites which cannot be enabled by any directive are [never] recorded, and do not cost
+//! anything after they are first seen. If there are no span directives, this applies to every
+//! callsite which is not enabled.
+//!
+//! [`ForestFireSubscriber`]: crate::ForestFireSubscriber
+//! [`ForestFireSubscriber::with_filter`]: crate::ForestFireSubscriber::with_filter
+//! [never]: tracing::subscriber::Interest::never
+
+use std::{
+    env,
+    error::Error,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use tracing::{Metadata, level_filters::LevelFilter, subscriber::Interest};
+
+/// A set of directives deciding which spans and events are recorded.
+///
+/// See the [module documentation](self) for the syntax.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    // sorted from least to most specific
+    statics: Vec<Directive>,
+    spans: Vec<Directive>,
+}
+
+#[derive(Debug, Clone)]
+struct Directive {
+    target: Option<String>,
+    span: Option<String>,
+    level: LevelFilter,
+}
+
+impl Directive {
+    fn matches_target(&self, target: &str) -> bool {
+        self.target
+            .as_deref()
+            .is_none_or(|prefix| target.starts_with(prefix))
+    }
+
+    fn specificity(&self) -> usize {
+        self.target.as_deref().map_or(0, |target| target.len() + 1)
+    }
+}
+
+impl Filter {
+    /// Parses a filter from a list of directives.
+    ///
+    /// This is the same as using [`str::parse`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the directives are malformed.
+    pub fn parse(directives: &str) -> Result<Self, ParseFilterError> {
+        let mut filter = Self::default();
+        for directive in directives.split(',').map(str::trim) {
+            if directive.is_empty() {
+                continue;
+            }
+            let directive = parse_directive(directive)?;
+            if directive.span.is_some() {
+                filter.spans.push(directive);
+            } else {
+                filter.statics.push(directive);
+            }
+        }
+        // stable, so later directives stay after earlier ones
+        filter.statics.sort_by_key(Directive::specificity);
+        Ok(filter)
+    }
+
+    /// Parses a filter from the environment variable `var`, or from `default` if the variable
+    /// is not set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directives are malformed, or if the variable is not valid
+    /// unicode.
+    pub fn from_env(var: &str, default: &str) -> Result<Self, ParseFilterError> {
+        match env::var(var) {
+            Ok(directives) => Self::parse(&directives),
+            Err(env::VarError::NotPresent) => Self::parse(default),
+            Err(env::VarError::NotUnicode(_)) => Err(ParseFilterError::new(format!(
+                "environment variable {var} is not valid unicode"
+            ))),
+        }
+    }
+
+    /// Returns the most verbose level which may be enabled by this filter.
+    pub fn max_level(&self) -> LevelFilter {
+        self.statics
+            .iter()
+            .chain(&self.spans)
+            .map(|directive| directive.level)
+            .max()
+            .unwrap_or(LevelFilter::OFF)
+    }
+
+    /// Returns whether this filter contains any span directives.
+    ///
+    /// Without them, whether something is enabled only depends on its metadata.
+    pub fn has_span_directives(&self) -> bool {
+        !self.spans.is_empty()
+    }
+
+    /// Returns whether the given metadata is enabled, regardless of which spans it is in.
+    pub fn enabled_statically(&self, metadata: &Metadata<'_>) -> bool {
+        self.statics
+            .iter()
+            .rev()
+            .find(|directive| directive.matches_target(metadata.target()))
+            .is_some_and(|directive| *metadata.level() <= directive.level)
+    }
+
+    /// Returns the level enabled inside of a span with the given metadata by span
+    /// directives, or `None` if no span directive matches it.
+    pub fn span_scope(&self, metadata: &Metadata<'_>) -> Option<LevelFilter> {
+        if !metadata.is_span() {
+            return None;
+        }
+        self.spans
+            .iter()
+            .filter(|directive| {
+                directive.span.as_deref() == Some(metadata.name())
+                    && directive.matches_target(metadata.target())
+            })
+            .map(|directive| directive.level)
+            .max()
+    }
+
+    /// Returns whether the given metadata is enabled inside of a span scope enabling
+    /// `scope` (see [`Filter::span_scope`]).
+    ///
+    /// Spans matching a span directive are themselves enabled at that directive's level.
+    pub fn enabled(&self, metadata: &Metadata<'_>, scope: Option<LevelFilter>) -> bool {
+        let level = *metadata.level();
+        self.enabled_statically(metadata)
+            || scope.is_some_and(|scope| level <= scope)
+            || self
+                .span_scope(metadata)
+                .is_some_and(|scope| level <= scope)
+    }
+
+    /// Returns the [`Interest`] a subscriber using this filter has in a callsite.
+    pub fn interest(&self, metadata: &Metadata<'_>) -> Interest {
+        if self.enabled_statically(metadata) {
+            Interest::always()
+        } else if self
+            .spans
+            .iter()
+            .any(|directive| *metadata.level() <= directive.level)
+        {
+            // may be enabled inside of a span, or it may be one of the spans itself
+            Interest::sometimes()
+        } else {
+            Interest::never()
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = ParseFilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+fn parse_directive(directive: &str) -> Result<Directive, ParseFilterError> {
+    let (selector, level) = match directive.split_once('=') {
+        Some((selector, level)) => (selector.trim(), Some(parse_level(level.trim())?)),
+        None => (directive, None),
+    };
+    if selector.is_empty() {
+        return Err(ParseFilterError::new(format!(
+            "missing target or span in directive `{directive}`"
+        )));
+    }
+
+    let (target, span) = match selector.split_once('[') {
+        Some((target, span)) => {
+            let span = span.strip_suffix(']').ok_or_else(|| {
+                ParseFilterError::new(format!("unclosed `[` in directive `{directive}`"))
+            })?;
+            if span.contains(['{', '}', '[', ']']) {
+                return Err(ParseFilterError::new(format!(
+                    "span field filters are not supported (in directive `{directive}`)"
+                )));
+            }
+            (target.trim(), Some(span.trim()))
+        }
+        None => (selector, None),
+    };
+
+    let span = match span {
+        Some("") => {
+            return Err(ParseFilterError::new(format!(
+                "empty span name in directive `{directive}`"
+            )));
+        }
+        span => span.map(str::to_owned),
+    };
+    // a lone level is a global directive
+    if level.is_none()
+        && span.is_none()
+        && let Ok(level) = parse_level(target)
+    {
+        return Ok(Directive {
+            target: None,
+            span: None,
+            level,
+        });
+    }
+
+    Ok(Directive {
+        target: (!target.is_empty()).then(|| target.to_owned()),
+        span,
+        level: level.unwrap_or(LevelFilter::TRACE),
+    })
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, ParseFilterError> {
+    level
+        .parse()
+        .map_err(|_| ParseFilterError::new(format!("invalid level `{level}`")))
+}
+
+/// An error returned when a [`Filter`] cannot be parsed.
+#[derive(Debug, Clone)]
+pub struct ParseFilterError {
+    message: String,
+}
+
+impl ParseFilterError {
+    fn new(message: String) -> Self {
+        Self { message }
+    }
+}
+
+impl Display for ParseFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid filter: {}", self.message)
+    }
+}
+
+impl Error for ParseFilterError {}
@@ -0,0 +1,91 @@
+//! Standalone HTML reports.
+//!
+//! [`write_html`] writes a single HTML file containing both the fireWatch viewer and the
+//! trace, so it can be opened with a double-click and doesn't need network access:
+//!
+//! ```no_run
+//! use std::fs::File;
+//!
+//! use forrust_fire_tracing::providers::{ProviderExt, log::LogEventProvider};
+//!
+//! let log_ashes = LogEventProvider::new().run(|| {
+//!     tracing::info!("hello!");
+//! });
+//! let file = File::create("report.html").unwrap();
+//! forrust_fire_tracing::html::write_html(&log_ashes, file).unwrap();
+//! ```
+//!
+//! The viewer is a bundled copy of fireWatch; it is regenerated from the `firewatch`
+//! directory of the repository with `npm run report`.
+
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+const PAGE: &str = include_str!("../firewatch/index.html");
+const SCRIPT: &str = include_str!("../firewatch/firewatch.js");
+const STYLE: &str = include_str!("../firewatch/style.css");
+
+// the parts of `PAGE` which are replaced by the inlined script and style
+const SCRIPT_TAG: &str = r#"<script src="./index.ts" type="module"></script>"#;
+const STYLE_TAG: &str = r#"<link href="./style.css" rel="stylesheet" />"#;
+// the font is left out, since it would be loaded from the network
+const FONT_TAG: &str = concat!(
+    r#"<link href="https://fonts.googleapis.com/css2?family=Ubuntu+Mono:ital,wght@0,400;0,700;"#,
+    r#"1,400;1,700&display=swap" rel="stylesheet" />"#,
+);
+const BODY_END: &str = "</body>";
+
+/// Writes `trace` into `writer` as a standalone HTML page which displays it.
+///
+/// `trace` is usually a [`LogAshes`], but anything serializing into the same format works;
+/// for example, an `Ashes<serde_json::Value>` loaded back from a JSON trace.
+///
+/// Writes are small, so `writer` should be buffered.
+///
+/// See the [module documentation](self).
+///
+/// [`LogAshes`]: crate::providers::log::LogAshes
+pub fn write_html(trace: &(impl Serialize + ?Sized), mut writer: impl Write) -> io::Result<()> {
+    let (head, rest) = PAGE.split_once(SCRIPT_TAG).expect("script tag");
+    let (between, rest) = rest.split_once(STYLE_TAG).expect("style tag");
+    let (after_style, rest) = rest.split_once(FONT_TAG).expect("font tag");
+    let (body, tail) = rest.rsplit_once(BODY_END).expect("end of body");
+
+    write!(
+        writer,
+        "{head}<script type=\"module\">\n{SCRIPT}</script>{between}<style>\n{STYLE}</style>\
+         {after_style}{body}"
+    )?;
+    writer.write_all(br#"<script id="embedded-trace" type="application/json">"#)?;
+    serde_json::to_writer(EscapeScript(&mut writer), trace)?;
+    write!(writer, "</script>\n{BODY_END}{tail}")
+}
+
+/// Escapes `<` in JSON written into a `<script>` element, so that strings in the trace
+/// can't end the element early.
+struct EscapeScript<W>(W);
+
+impl<W: Write> Write for EscapeScript<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        // `<` only ever appears inside of JSON strings, where `\u003c` means the same thing
+        let mut parts = buf.split(|&b| b == b'<');
+        if let Some(first) = parts.next() {
+            self.0.write_all(first)?;
+        }
+        for part in parts {
+            self.0.write_all(br"\u003c")?;
+            self.0.write_all(part)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
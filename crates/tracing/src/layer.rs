@@ -0,0 +1,320 @@
+//! A [`Layer`] version of [`ForestFireSubscriber`], for use with `tracing-subscriber`.
+//!
+//! [`ForestFireSubscriber`] is a standalone [`Subscriber`], so it cannot be combined with
+//! other subscribers. [`ForestFireLayer`] records the same tree, but as a layer on top of a
+//! [`Registry`], next to any other layers (such as `tracing_subscriber::fmt`):
+//!
+//! ```
+//! use forrust_fire_tracing::{layer::ForestFireLayer, providers::log::LogEventProvider};
+//! use tracing_subscriber::{Registry, layer::SubscriberExt};
+//!
+//! let layer = ForestFireLayer::new(LogEventProvider::new());
+//! let handle = layer.handle();
+//! let subscriber = Registry::default().with(layer);
+//!
+//! tracing::subscriber::with_default(subscriber, || {
+//!     tracing::info_span!("hello").in_scope(|| tracing::info!("world"));
+//! });
+//!
+//! // the subscriber (and the layer with it) has been dropped, so we can burn the tree
+//! let ash_trayce = handle.burn();
+//! assert_eq!(ash_trayce.ash.root().n_children(), 1);
+//! ```
+//!
+//! Spans are tracked by the registry; the layer only stores the [`fire::BranchId`] of each
+//! span in its [extensions]. Parents are resolved through the registry too, so if a span's
+//! parent was filtered out for this layer, the span is placed under the closest ancestor
+//! which was not.
+//!
+//! [`ForestFireSubscriber`]: crate::ForestFireSubscriber
+//! [`Subscriber`]: tracing::Subscriber
+//! [`Registry`]: tracing_subscriber::Registry
+//! [extensions]: tracing_subscriber::registry::SpanRef::extensions
+
+use std::{
+    fmt::{self, Debug},
+    marker::PhantomData,
+    sync::{Arc, Mutex, MutexGuard},
+    thread,
+};
+
+use forrust_fire_tree::fire::{self, ForestFire};
+use tracing::{Subscriber, span};
+use tracing_subscriber::{
+    layer::{Context, Layer},
+    registry::{LookupSpan, SpanRef},
+};
+
+use crate::{
+    AshTrayce, EventInfo, EventProvider, Fields, Inner, Inside, budget::DroppedNodes,
+    mutex_into_inner_ignore_poison, mutex_lock_ignore_poison, panic,
+};
+
+/// A [`Layer`] which records spans & events into a tree, just like [`ForestFireSubscriber`].
+///
+/// Since the layer is moved into the subscriber, the tree is retrieved through a
+/// [`ForestFireHandle`]. See the [module documentation](self) for an example.
+///
+/// [`ForestFireSubscriber`]: crate::ForestFireSubscriber
+pub struct ForestFireLayer<P: EventProvider> {
+    inner: Arc<Mutex<Inner<P>>>,
+}
+
+/// A handle to the tree recorded by a [`ForestFireLayer`], created through
+/// [`ForestFireLayer::handle`].
+pub struct ForestFireHandle<P: EventProvider> {
+    inner: Arc<Mutex<Inner<P>>>,
+}
+
+// stored in span extensions. generic over `P` so that multiple layers with
+// different providers don't overwrite each other's branches
+struct LayerBranch<P> {
+    id: fire::BranchId,
+    phantom: PhantomData<fn() -> P>,
+}
+
+impl<P: EventProvider> ForestFireLayer<P> {
+    /// Creates a new layer recording into an empty tree.
+    pub fn new(provider: P) -> Self {
+        Self::with_forest(ForestFire::new(), provider)
+    }
+
+    /// Creates a new layer.
+    ///
+    /// The provided `forest` tree will not be cleared, and new nodes will
+    /// be added starting from root.
+    pub fn with_forest(forest: ForestFire<P::Event>, provider: P) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner::new(forest, provider))),
+        }
+    }
+
+    /// Returns a handle through which the tree can be burned once the layer is dropped.
+    pub fn handle(&self) -> ForestFireHandle<P> {
+        ForestFireHandle {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+
+    fn inner(&self) -> MutexGuard<'_, Inner<P>> {
+        mutex_lock_ignore_poison(&self.inner)
+    }
+}
+
+impl<P: EventProvider> ForestFireHandle<P> {
+    /// Returns the number of logged nodes.
+    pub fn node_count(&self) -> usize {
+        mutex_lock_ignore_poison(&self.inner).forest.node_count()
+    }
+
+    /// Finishes up the tree.
+    ///
+    /// Please see [ForestFire::burn] for performance considerations.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the [`ForestFireLayer`] (or any other handle) is still alive. Make sure the
+    /// subscriber containing the layer has been dropped first.
+    pub fn burn(self) -> AshTrayce<P> {
+        let inner = Arc::into_inner(self.inner).unwrap_or_else(|| {
+            panic!("cannot burn the tree while its ForestFireLayer or another handle is alive")
+        });
+        let inner = mutex_into_inner_ignore_poison(inner);
+        AshTrayce {
+            ash: inner.forest.burn(),
+            provider: inner.provider,
+            dropped: DroppedNodes::default(),
+        }
+    }
+}
+
+/// Returns the branch of the given span, or of its closest ancestor which has one.
+fn branch_of<P: EventProvider, S>(span: Option<SpanRef<'_, S>>) -> fire::BranchId
+where
+    S: for<'a> LookupSpan<'a>,
+{
+    span.into_iter()
+        .flat_map(|span| span.scope())
+        .find_map(|span| span.extensions().get::<LayerBranch<P>>().map(|b| b.id))
+        .unwrap_or(fire::BranchId::ROOT)
+}
+
+fn get_branch<P: EventProvider, S>(id: &span::Id, ctx: &Context<'_, S>) -> Option<fire::BranchId>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let span = ctx.span(id)?;
+    let extensions = span.extensions();
+    extensions.get::<LayerBranch<P>>().map(|b| b.id)
+}
+
+impl<P, S> Layer<S> for ForestFireLayer<P>
+where
+    P: EventProvider + Send,
+    P::Event: Send,
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let parent_span = if let Some(parent) = attrs.parent() {
+            ctx.span(parent)
+        } else if attrs.is_contextual() {
+            ctx.lookup_current()
+        } else {
+            None
+        };
+        let parent = branch_of::<P, S>(parent_span);
+        let thread = thread::current();
+
+        let _inside = Inside::enter();
+        let mut inner = self.inner();
+        let inner = &mut *inner;
+        let branch = inner.forest.next_id();
+        let event = inner.provider.make_event(
+            branch.value(),
+            EventInfo {
+                is_span: true,
+                fields: Fields::Full(attrs.fields()),
+                metadata: attrs.metadata(),
+                values_early: Some(attrs.values()),
+                thread: &thread,
+            },
+        );
+        inner.forest.branch(parent, event);
+        if inner.provider.should_use_visitor_if_values_given() {
+            let payload = inner.forest.payload_mut(branch);
+            attrs
+                .values()
+                .record(&mut inner.provider.make_visitor(branch.value(), payload));
+        }
+        inner.node_created(branch, parent);
+
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(LayerBranch::<P> {
+                id: branch,
+                phantom: PhantomData,
+            });
+        }
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        if !P::should_use_visitor() {
+            return;
+        }
+        let Some(branch) = get_branch::<P, S>(id, &ctx) else {
+            return;
+        };
+        let _inside = Inside::enter();
+        let mut inner = self.inner();
+        let inner = &mut *inner;
+        let payload = inner.forest.payload_mut(branch);
+        values.record(&mut inner.provider.make_visitor(branch.value(), payload));
+    }
+
+    fn on_follows_from(&self, id: &span::Id, follows: &span::Id, ctx: Context<'_, S>) {
+        if !P::should_span_follows_from() {
+            return;
+        }
+        let (Some(branch), Some(follows)) = (
+            get_branch::<P, S>(id, &ctx),
+            get_branch::<P, S>(follows, &ctx),
+        ) else {
+            return;
+        };
+        let _inside = Inside::enter();
+        let mut inner = self.inner();
+        let inner = &mut *inner;
+        let payload = inner.forest.payload_mut(branch);
+        inner
+            .provider
+            .span_follows_from(branch.value(), payload, follows.value());
+    }
+
+    fn on_event(&self, event: &tracing::Event<'_>, ctx: Context<'_, S>) {
+        let parent = branch_of::<P, S>(ctx.event_span(event));
+        let thread = thread::current();
+
+        // the panic might come from this layer on this thread, see `Inside`
+        if panic::is_panic(event.metadata()) && Inside::is_set() {
+            return;
+        }
+        let _inside = Inside::enter();
+        let mut inner = self.inner();
+        let inner = &mut *inner;
+        let branch = inner.forest.next_id();
+        let payload = inner.provider.make_event(
+            branch.value(),
+            EventInfo {
+                is_span: false,
+                fields: Fields::Iter(event.fields()),
+                metadata: event.metadata(),
+                values_early: None,
+                thread: &thread,
+            },
+        );
+        inner.forest.branch(parent, payload);
+        if P::should_use_visitor() {
+            let payload = inner.forest.payload_mut(branch);
+            event.record(&mut inner.provider.make_visitor(branch.value(), payload));
+        }
+        inner.node_created(branch, parent);
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        if !P::should_span_enter() {
+            return;
+        }
+        let Some(branch) = get_branch::<P, S>(id, &ctx) else {
+            return;
+        };
+        let _inside = Inside::enter();
+        let mut inner = self.inner();
+        let inner = &mut *inner;
+        let payload = inner.forest.payload_mut(branch);
+        inner.provider.span_enter(branch.value(), payload);
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        if !P::should_span_exit() {
+            return;
+        }
+        let Some(branch) = get_branch::<P, S>(id, &ctx) else {
+            return;
+        };
+        let _inside = Inside::enter();
+        let mut inner = self.inner();
+        let inner = &mut *inner;
+        let payload = inner.forest.payload_mut(branch);
+        inner
+            .provider
+            .span_exit(branch.value(), payload, thread::panicking());
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        // the registry already counts references for us
+        if !P::should_span_close() {
+            return;
+        }
+        let Some(branch) = get_branch::<P, S>(&id, &ctx) else {
+            return;
+        };
+        let _inside = Inside::enter();
+        let mut inner = self.inner();
+        let inner = &mut *inner;
+        let payload = inner.forest.payload_mut(branch);
+        inner
+            .provider
+            .span_close(branch.value(), payload, thread::panicking());
+    }
+}
+
+impl<P: EventProvider> Debug for ForestFireLayer<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ForestFireLayer").finish_non_exhaustive()
+    }
+}
+
+impl<P: EventProvider> Debug for ForestFireHandle<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ForestFireHandle").finish_non_exhaustive()
+    }
+}
@@ -3,20 +3,42 @@
 //! The main focus of this crate is [`ForestFireSubscriber`], which is able to build a tree of tracing
 //! events & spans. Since it is based on [`ForestFire`] which allows inserting nodes into any part
 //! of the tree, `ForestFireSubscriber` is capable of tracing multithreaded applications (note that
-//! the state is behind a mutex, so some locking will be required!).
+//! the state is behind a mutex, so some locking will be required! events can avoid it though, see
+//! [`EventProvider::fork`]). [`run_forest`] runs a closure which can spawn scoped threads that all
+//! record into the same tree.
 //!
 //! `ForestFireSubscriber` is generic over the data it collects from tracing events; the event
 //! creation mechanism is specified by your [`EventProvider`] implementation, or you can simply
 //! use the built-in [`LogEventProvider`] which will collect most of the data you'd likely want
-//! from tracing (save for timing information).
+//! from tracing, including how long each span was busy for.
+//!
+//! To use it alongside other `tracing-subscriber` layers, enable the `tracing-subscriber` feature
+//! and use the [`layer`] module instead.
+//!
+//! What gets recorded can be narrowed down with a [filter], written like `RUST_LOG`, and
+//! limited with a [budget].
+//!
+//! If the process might not live long enough to burn the tree (crashes, aborts, OOM kills),
+//! nodes can also be [streamed](stream) to a file as they are created.
+//!
+//! Panics can be recorded into the tree as well, by installing a [panic hook](mod@panic).
+//!
+//! With the `html` feature, a trace can be saved as a standalone [HTML report](html) which
+//! opens in the fireWatch viewer.
 //!
 //! [`LogEventProvider`]: crate::providers::log::LogEventProvider
 
 #![warn(missing_docs)]
 
 use std::{
+    cell::Cell,
+    collections::{BTreeMap, VecDeque},
+    fmt,
     num::NonZeroU64,
-    sync::{Arc, Mutex, MutexGuard},
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicBool, Ordering},
+    },
     thread::{self, ThreadId},
 };
 
@@ -26,12 +48,29 @@ use forrust_fire_tree::{
 };
 use thread_local::ThreadLocal;
 use tracing::{
-    Subscriber,
+    Dispatch, Subscriber,
     field::{self, ValueSet},
+    level_filters::LevelFilter,
     span,
+    subscriber::Interest,
+};
+use tracing_core::span::Current;
+
+use crate::{
+    budget::{Budget, DroppedNodes, RingBuffer},
+    filter::Filter,
 };
 
+pub mod budget;
+pub mod filter;
+#[cfg(feature = "html")]
+pub mod html;
+#[cfg(feature = "tracing-subscriber")]
+pub mod layer;
+pub mod panic;
 pub mod providers;
+#[cfg(feature = "serde")]
+pub mod stream;
 #[cfg(test)]
 mod test;
 
@@ -66,6 +105,11 @@ pub struct EventInfo<'a> {
     ///
     /// [visitor]: EventProvider::make_visitor
     pub values_early: Option<&'a ValueSet<'a>>,
+    /// The thread on which the event was created.
+    ///
+    /// Its [ID](thread::Thread::id) and [name](thread::Thread::name) can be used to tell
+    /// apart events recorded by different threads.
+    pub thread: &'a thread::Thread,
 }
 
 /// Definition of how to create and manage events captured by [`ForestFireSubscriber`].
@@ -76,6 +120,9 @@ pub struct EventInfo<'a> {
 /// of features are provided by default that you can disable to improve performance. (mainly
 /// look out for `should_*` functions).
 ///
+/// Every method is called while (some of) the subscriber's state is locked, so they must not
+/// emit tracing events or spans to the same subscriber themselves, as that would deadlock.
+///
 /// [`LogEventProvider`]: crate::providers::log::LogEventProvider
 pub trait EventProvider: 'static {
     /// The type representing the events being captured.
@@ -107,13 +154,18 @@ pub trait EventProvider: 'static {
 
     /// Called whenever a span is exited.
     ///
+    /// `unwinding` is whether the exiting thread is [unwinding from a panic], which is
+    /// how spans get exited when a panic passes through them.
+    ///
     /// The default implementation does nothing.
     ///
     /// If you're not going to override this function, you should likely override
     /// [`EventProvider::should_span_exit`] to prevent unneeded mutex locking.
+    ///
+    /// [unwinding from a panic]: std::thread::panicking
     #[inline]
-    fn span_exit(&mut self, id: usize, event: &mut Self::Event) {
-        let _ = (id, event);
+    fn span_exit(&mut self, id: usize, event: &mut Self::Event, unwinding: bool) {
+        let _ = (id, event, unwinding);
     }
 
     /// Whether to call [`EventProvider::span_exit`].
@@ -126,6 +178,55 @@ pub trait EventProvider: 'static {
         true
     }
 
+    /// Called whenever a span is closed, i.e. once every handle to it has been dropped.
+    ///
+    /// `unwinding` is whether the thread dropping the last handle is [unwinding from a
+    /// panic].
+    ///
+    /// The default implementation does nothing.
+    ///
+    /// If you're not going to override this function, you may override
+    /// [`EventProvider::should_span_close`] to skip calling it.
+    ///
+    /// [unwinding from a panic]: std::thread::panicking
+    #[inline]
+    fn span_close(&mut self, id: usize, event: &mut Self::Event, unwinding: bool) {
+        let _ = (id, event, unwinding);
+    }
+
+    /// Whether to call [`EventProvider::span_close`].
+    ///
+    /// The subscriber keeps a reference count for every span regardless (it is needed for
+    /// [`Span::current`]), so unlike the other hooks, this does not save any locking.
+    /// The default implementation returns `true`.
+    ///
+    /// [`Span::current`]: tracing::Span::current
+    #[inline]
+    fn should_span_close() -> bool {
+        true
+    }
+
+    /// Called whenever a span is marked as [following from] another span.
+    ///
+    /// `id` and `event` belong to the span which follows from the span `follows`. Both
+    /// of them are always spans. The default implementation does nothing.
+    ///
+    /// [following from]: tracing::Span::follows_from
+    #[inline]
+    fn span_follows_from(&mut self, id: usize, event: &mut Self::Event, follows: usize) {
+        let _ = (id, event, follows);
+    }
+
+    /// Whether to call [`EventProvider::span_follows_from`].
+    ///
+    /// If this is enabled, the tree mutex is locked whenever a follows-from relationship
+    /// is recorded. If you do not have any custom logic in `span_follows_from`, you should
+    /// likely override this and return `false`. The default implementation returns `true`.
+    #[inline]
+    fn should_span_follows_from() -> bool {
+        true
+    }
+
     /// Create a [visitor] to do something with an event's fields.
     ///
     /// The default implementation returns a visitor which does not do anything. If
@@ -165,21 +266,179 @@ pub trait EventProvider: 'static {
     fn should_use_visitor_if_values_given(&self) -> bool {
         Self::should_use_visitor()
     }
+
+    /// Returns roughly how many bytes of memory the provider holds on to, which is what
+    /// [`Budget::max_bytes`] limits.
+    ///
+    /// The default implementation returns 0, so byte limits never run out.
+    fn used_bytes(&self) -> usize {
+        0
+    }
+
+    /// Called after nodes have been removed from the tree, which happens when a
+    /// [`RingBuffer`] evicts old subtrees.
+    ///
+    /// `removed` yields the ID and event of every removed node, and `remaining` those of
+    /// every node still in the tree, both in order of creation. This is the place to reclaim
+    /// any memory the provider holds for the removed nodes.
+    ///
+    /// The default implementation drops the removed events.
+    fn nodes_removed<'a>(
+        &mut self,
+        removed: impl Iterator<Item = (usize, Self::Event)>,
+        remaining: impl Iterator<Item = (usize, &'a mut Self::Event)>,
+    ) where
+        Self::Event: 'a,
+    {
+        let _ = (removed, remaining);
+    }
+
+    /// Creates an empty provider for creating the events of a single thread, which lets
+    /// [`ForestFireSubscriber`] record events without locking its shared state.
+    ///
+    /// Each thread gets its own fork. Events created through it are buffered by the thread,
+    /// and handed to [`merge`] before they are placed into the tree, see
+    /// [`ForestFireSubscriber`](ForestFireSubscriber#recording-events) for when that happens.
+    /// Since their node doesn't exist yet, they are created with [`usize::MAX`] as their ID.
+    /// Spans are always created by `self`.
+    ///
+    /// Forks are created with [`Fork::new`], which requires the provider and its events to be
+    /// [`Send`].
+    ///
+    /// Forks are not used while a [`Budget`], [`RingBuffer`] or stream is set, as those need
+    /// to see every node as soon as it is created.
+    ///
+    /// The default implementation returns `None`, so every event is created by `self` while
+    /// the subscriber's state is locked.
+    ///
+    /// [`merge`]: EventProvider::merge
+    fn fork(&self) -> Option<Fork<Self>>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// Takes over the `events` created through `fork` since it was last merged, in order of
+    /// creation, leaving `fork` ready to create more.
+    ///
+    /// This is where anything the events refer to within `fork` should be moved into `self`.
+    ///
+    /// The default implementation does nothing, which is only correct if events don't refer
+    /// to their provider.
+    fn merge<'a>(&mut self, fork: &mut Self, events: impl Iterator<Item = &'a mut Self::Event>)
+    where
+        Self: Sized,
+        Self::Event: 'a,
+    {
+        let _ = (fork, events);
+    }
+}
+
+/// A provider created by [`EventProvider::fork`], along with the events it created which
+/// haven't been placed into the tree yet.
+pub struct Fork<P: EventProvider> {
+    // behind `dyn Send` so that forks can be moved between threads without every provider
+    // having to be `Send`; `Fork::new` is where the buffer is checked to be
+    buffer: Box<dyn ForkBuffer<P> + Send>,
+}
+
+struct Buffer<P: EventProvider> {
+    provider: P,
+    // in the order they were recorded, along with the branch to place them into
+    events: Vec<(fire::BranchId, P::Event)>,
+}
+
+trait ForkBuffer<P: EventProvider> {
+    fn get(&self) -> &Buffer<P>;
+    fn get_mut(&mut self) -> &mut Buffer<P>;
+}
+
+impl<P: EventProvider> ForkBuffer<P> for Buffer<P> {
+    fn get(&self) -> &Buffer<P> {
+        self
+    }
+
+    fn get_mut(&mut self) -> &mut Buffer<P> {
+        self
+    }
+}
+
+impl<P: EventProvider> Fork<P> {
+    /// Wraps `provider` to create the events of a single thread.
+    ///
+    /// Forks are moved between threads, so only providers which are [`Send`], along with
+    /// their events, can be forked.
+    pub fn new(provider: P) -> Self
+    where
+        P: Send,
+        P::Event: Send,
+    {
+        Self {
+            buffer: Box::new(Buffer {
+                provider,
+                events: Vec::new(),
+            }),
+        }
+    }
+}
+
+impl<P: EventProvider> fmt::Debug for Fork<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Fork")
+            .field("events", &self.buffer.get().events.len())
+            .finish_non_exhaustive()
+    }
 }
 
 // note: we assume that a Local is fine to read even if it's been poisoned
 //       keep that in mind if we add any more functionality
-struct Local {
+struct Local<P: EventProvider> {
     stack: Vec<fire::BranchId>,
+    // entered spans which enable more through span directives, see `Filter::span_scope`
+    scopes: Vec<(fire::BranchId, LevelFilter)>,
     last_using_thread: ThreadId,
+    // creates and buffers the events of this thread, if the provider can be forked
+    fork: Option<Fork<P>>,
 }
 
-impl Default for Local {
-    fn default() -> Self {
+impl<P: EventProvider> Local<P> {
+    fn new(fork: Option<Fork<P>>) -> Self {
         Self {
-            stack: Default::default(),
+            stack: Vec::new(),
+            scopes: Vec::new(),
             last_using_thread: thread::current().id(),
+            fork,
+        }
+    }
+
+    /// Forgets the spans of a thread which has exited, if its slot is now used by the current
+    /// thread.
+    fn claim(&mut self) {
+        let current = thread::current().id();
+        // `ThreadLocal` reuses the slots of threads which have exited, so the
+        // stack may still hold the spans of a dead thread
+        if self.last_using_thread != current {
+            self.last_using_thread = current;
+            self.stack.clear();
+            self.scopes.clear();
+        }
+    }
+
+    fn scope(&self) -> Option<LevelFilter> {
+        self.scopes.iter().map(|&(_, level)| level).max()
+    }
+
+    /// Removes the most recent entry of `span`, returning whether there was one.
+    fn exit(&mut self, span: fire::BranchId) -> bool {
+        let Some(pos) = self.stack.iter().rposition(|&id| id == span) else {
+            return false;
+        };
+        self.stack.remove(pos);
+        if let Some(pos) = self.scopes.iter().rposition(|&(id, _)| id == span) {
+            self.scopes.remove(pos);
         }
+        true
     }
 }
 
@@ -188,6 +447,225 @@ impl Default for Local {
 struct Inner<P: EventProvider> {
     forest: ForestFire<P::Event>,
     provider: P,
+    // reference counts of spans which haven't been closed yet
+    span_refs: BTreeMap<fire::BranchId, usize>,
+    // metadata of spans which haven't been closed yet, for `current_span`
+    span_metadata: BTreeMap<fire::BranchId, &'static tracing::Metadata<'static>>,
+    // spans which matched a span directive of the filter
+    span_scopes: BTreeMap<fire::BranchId, LevelFilter>,
+    budget: Option<Budget>,
+    // spans dropped because of the budget, mapped to their closest recorded ancestor.
+    // their IDs are handed out from `DROPPED_START` upwards, see `Inner::is_dropped`
+    dropped_spans: BTreeMap<fire::BranchId, fire::BranchId>,
+    next_dropped: usize,
+    dropped: DroppedNodes,
+    ring_buffer: Option<RingBuffer>,
+    // completed root-level subtrees, oldest first. only tracked with a ring buffer
+    completed: VecDeque<fire::BranchId>,
+    #[cfg(feature = "serde")]
+    stream: Option<stream::NodeStream<P>>,
+}
+
+// way past any ID the forest will reach
+const DROPPED_START: usize = usize::MAX / 2 + 1;
+
+impl<P: EventProvider> Inner<P> {
+    const fn new(forest: ForestFire<P::Event>, provider: P) -> Self {
+        Self {
+            forest,
+            provider,
+            span_refs: BTreeMap::new(),
+            span_metadata: BTreeMap::new(),
+            span_scopes: BTreeMap::new(),
+            budget: None,
+            dropped_spans: BTreeMap::new(),
+            next_dropped: DROPPED_START,
+            dropped: DroppedNodes::new(),
+            ring_buffer: None,
+            completed: VecDeque::new(),
+            #[cfg(feature = "serde")]
+            stream: None,
+        }
+    }
+
+    /// Returns whether `id` belongs to a span which was dropped because of the budget.
+    fn is_dropped(id: fire::BranchId) -> bool {
+        id.value() >= DROPPED_START
+    }
+
+    /// Decides whether a new node is recorded, given the branch it would be placed into.
+    ///
+    /// Returns the branch to place the node into, or the closest recorded ancestor the
+    /// dropped node was counted under.
+    fn admit(
+        &mut self,
+        parent: fire::BranchId,
+        metadata: &tracing::Metadata<'_>,
+    ) -> Result<fire::BranchId, fire::BranchId> {
+        let (parent, in_dropped) = match self.dropped_spans.get(&parent) {
+            Some(&ancestor) => (ancestor, true),
+            None => (parent, false),
+        };
+        // evicted subtrees are not counted as dropped, they are gone altogether
+        if self.forest.was_removed(parent) {
+            return Err(parent);
+        }
+        let Some(budget) = &self.budget else {
+            return Ok(parent);
+        };
+        let keep = if in_dropped
+            || budget.exceeded(self.forest.node_count(), self.provider.used_bytes())
+        {
+            budget.keeps(*metadata.level(), parent.is_root(), in_dropped)
+        } else {
+            true
+        };
+        if keep {
+            Ok(parent)
+        } else {
+            self.dropped
+                .add((!parent.is_root()).then(|| parent.value()));
+            Err(parent)
+        }
+    }
+
+    /// Called once a root-level node and everything inside of it is complete, evicting
+    /// the oldest complete subtrees if the ring buffer is full.
+    fn subtree_completed(&mut self, id: fire::BranchId) {
+        let Some(RingBuffer {
+            max_subtrees,
+            max_bytes,
+        }) = self.ring_buffer
+        else {
+            return;
+        };
+        self.completed.push_back(id);
+
+        let mut evicted = Vec::new();
+        if let Some(max) = max_subtrees {
+            let excess = self.completed.len().saturating_sub(max);
+            evicted.extend(self.completed.drain(..excess));
+        }
+        self.remove_subtrees(&evicted);
+        if let Some(max) = max_bytes {
+            // the size of each subtree is unknown, so they are evicted one by one
+            while self.provider.used_bytes() > max
+                && let Some(oldest) = self.completed.pop_front()
+            {
+                self.remove_subtrees(&[oldest]);
+            }
+        }
+    }
+
+    fn remove_subtrees(&mut self, subtrees: &[fire::BranchId]) {
+        if subtrees.is_empty() {
+            return;
+        }
+        let removed = self.forest.remove_subtrees(subtrees);
+        for (id, _) in &removed {
+            self.dropped.forget(id.value());
+        }
+        self.provider.nodes_removed(
+            removed.into_iter().map(|(id, event)| (id.value(), event)),
+            self.forest
+                .payloads_mut()
+                .map(|(id, event)| (id.value(), event)),
+        );
+    }
+
+    /// Makes up an ID for a dropped span, counted under `ancestor`.
+    fn drop_span(
+        &mut self,
+        ancestor: fire::BranchId,
+        metadata: &'static tracing::Metadata<'static>,
+    ) -> fire::BranchId {
+        let id = fire::BranchId::new(self.next_dropped);
+        self.next_dropped += 1;
+        self.dropped_spans.insert(id, ancestor);
+        self.span_refs.insert(id, 1);
+        self.span_metadata.insert(id, metadata);
+        id
+    }
+
+    /// Forks the provider for a new thread, unless every node has to be seen as soon as it
+    /// is created.
+    fn fork(&self) -> Option<Fork<P>> {
+        if self.budget.is_some() || self.ring_buffer.is_some() {
+            return None;
+        }
+        #[cfg(feature = "serde")]
+        if self.stream.is_some() {
+            return None;
+        }
+        self.provider.fork()
+    }
+
+    /// Places the events buffered by a thread into the tree, in the order they were recorded.
+    fn merge_fork(&mut self, fork: &mut Fork<P>) {
+        let fork = fork.buffer.get_mut();
+        if fork.events.is_empty() {
+            return;
+        }
+        self.provider.merge(
+            &mut fork.provider,
+            fork.events.iter_mut().map(|(_, event)| event),
+        );
+        self.forest.reserve(fork.events.len());
+        for (parent, event) in fork.events.drain(..) {
+            self.forest.branch(parent, event);
+        }
+    }
+
+    /// Records an event into `parent` right away.
+    fn record_event(&mut self, parent: fire::BranchId, event: &tracing::Event<'_>) {
+        if let Some(explicit) = event.parent()
+            && !Self::is_dropped(parent)
+        {
+            ensure_normal(&self.forest, explicit, parent);
+        }
+        let Ok(parent) = self.admit(parent, event.metadata()) else {
+            return;
+        };
+        let id = self.forest.next_id();
+        let payload = self.provider.make_event(
+            id.value(),
+            EventInfo {
+                is_span: false,
+                fields: Fields::Iter(event.fields()),
+                metadata: event.metadata(),
+                values_early: None,
+                thread: &thread::current(),
+            },
+        );
+        self.forest.branch(parent, payload);
+        // let (_, fields) = add(
+        //     &mut inner,
+        //     parent,
+        //     Err(event.fields()),
+        //     event.metadata(),
+        //     false,
+        // );
+        if P::should_use_visitor() {
+            let payload = self.forest.payload_mut(id);
+
+            event.record(&mut self.provider.make_visitor(id.value(), payload));
+        }
+        self.node_created(id, parent);
+        if parent.is_root() {
+            self.subtree_completed(id);
+        }
+        // event.record(&mut inner.visitor(fields));
+    }
+
+    /// Called once a node has been fully created.
+    fn node_created(&mut self, id: fire::BranchId, parent: fire::BranchId) {
+        #[cfg(feature = "serde")]
+        if let Some(stream) = &mut self.stream {
+            stream.write(id, parent, &self.provider, self.forest.payload(id));
+        }
+        #[cfg(not(feature = "serde"))]
+        let _ = (id, parent);
+    }
 }
 
 /// An implementation of [`tracing::Subscriber`] which records the structured tracing data
@@ -198,10 +676,41 @@ struct Inner<P: EventProvider> {
 ///
 /// See the [crate documentation](crate) for more information.
 ///
+/// # Entering spans
+///
+/// Each thread keeps its own stack of entered spans, and new spans and events are placed
+/// into the span most recently entered on the thread creating them. Spans do not need to be
+/// entered and exited in order, which is what happens with [instrumented] futures that are
+/// interleaved on one thread or polled on several:
+///
+/// - A span may be entered multiple times, on one thread or on several at once.
+/// - Exiting a span removes its most recent entry on the current thread only, leaving any
+///   spans entered after it in place.
+/// - If the current thread has not entered the span, its entry is removed from the
+///   stack of another thread which has (as happens when a guard is moved between threads).
+///
+/// # Recording events
+///
+/// If the provider can be [forked](EventProvider::fork), events are recorded into a buffer
+/// of the thread creating them, without locking the tree (they still lock the thread's own
+/// stack of entered spans, which other threads rarely touch). A thread places its buffered
+/// events into the tree before creating a node under the lock (such as a span), and when a
+/// thread spawned through [`ForestScope`] exits. Whatever is left is placed into the tree
+/// when it is burned.
+///
+/// Buffered events keep their order relative to the other nodes created by the same thread,
+/// but there is no order between threads: events which several threads record into the same
+/// span may end up grouped by thread.
+///
+/// [instrumented]: tracing::Instrument
 /// [`ProviderExt::run`]: providers::ProviderExt::run
 pub struct ForestFireSubscriber<P: EventProvider> {
     inner: Mutex<Inner<P>>,
-    stack: ThreadLocal<Mutex<Local>>,
+    stack: ThreadLocal<Mutex<Local<P>>>,
+    filter: Option<Filter>,
+    // whether any thread has a fork of the provider, so `Local`s don't need to be looked
+    // at otherwise
+    buffering: AtomicBool,
 }
 
 impl<P: EventProvider> ForestFireSubscriber<P> {
@@ -211,24 +720,92 @@ impl<P: EventProvider> ForestFireSubscriber<P> {
     /// be added starting from root.
     pub const fn new(forest: ForestFire<P::Event>, provider: P) -> Self {
         Self {
-            inner: Mutex::new(Inner {
-                forest,
-                provider,
-                // string: String::new(),
-                // field_infos: Vec::new(),
-            }),
+            inner: Mutex::new(Inner::new(forest, provider)),
             stack: ThreadLocal::new(),
+            filter: None,
+            buffering: AtomicBool::new(false),
         }
     }
 
+    /// Makes this subscriber only record the spans and events enabled by `filter`.
+    ///
+    /// See the [`filter`] module for details. Any previously set filter is replaced.
+    ///
+    /// The filter should be set before the subscriber is used as a dispatcher, since
+    /// `tracing` caches whether callsites are enabled.
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Makes this subscriber stop growing the tree once `budget` runs out.
+    ///
+    /// See the [`budget`] module for details. Any previously set budget is replaced.
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        mutex_get_mut_ignore_poison(&mut self.inner).budget = Some(budget);
+        self
+    }
+
+    /// Makes this subscriber keep only the most recent root-level subtrees, as configured
+    /// by `ring_buffer`.
+    ///
+    /// See [`RingBuffer`] for details. Any previously set ring buffer is replaced.
+    pub fn with_ring_buffer(mut self, ring_buffer: RingBuffer) -> Self {
+        mutex_get_mut_ignore_poison(&mut self.inner).ring_buffer = Some(ring_buffer);
+        self
+    }
+
+    /// Makes this subscriber write every new node to `writer` as soon as it is created.
+    ///
+    /// See the [`stream`] module for the format and for loading it back. The writer is
+    /// flushed after every node, so it does not need to be wrapped in a [`BufWriter`].
+    ///
+    /// If writing fails, the stream is stopped and the error can be retrieved through
+    /// [`finish_stream`]. Any previously set stream is replaced.
+    ///
+    /// [`BufWriter`]: std::io::BufWriter
+    /// [`finish_stream`]: ForestFireSubscriber::finish_stream
+    #[cfg(feature = "serde")]
+    pub fn with_stream(mut self, writer: impl std::io::Write + Send + 'static) -> Self
+    where
+        P: stream::StreamEventProvider,
+    {
+        mutex_get_mut_ignore_poison(&mut self.inner).stream =
+            Some(stream::NodeStream::new(Box::new(writer)));
+        self
+    }
+
+    /// Stops streaming nodes and returns the writer given to [`with_stream`], or the first
+    /// error encountered while writing to it.
+    ///
+    /// Returns `None` if this subscriber was not streaming.
+    ///
+    /// [`with_stream`]: ForestFireSubscriber::with_stream
+    #[cfg(feature = "serde")]
+    pub fn finish_stream(&mut self) -> Option<std::io::Result<Box<dyn std::io::Write + Send>>> {
+        mutex_get_mut_ignore_poison(&mut self.inner)
+            .stream
+            .take()
+            .map(stream::NodeStream::finish)
+    }
+
     /// Finishes up this tree.
     ///
     /// Please see [ForestFire::burn] for performance considerations.
     pub fn burn(self) -> AshTrayce<P> {
-        let inner = mutex_into_inner_ignore_poison(self.inner);
+        let Self {
+            inner, mut stack, ..
+        } = self;
+        let mut inner = mutex_into_inner_ignore_poison(inner);
+        for local in stack.iter_mut().map(mutex_get_mut_ignore_poison) {
+            if let Some(fork) = &mut local.fork {
+                inner.merge_fork(fork);
+            }
+        }
         let ash = inner.forest.burn();
         AshTrayce {
             ash,
+            dropped: inner.dropped,
             provider: inner.provider, // string: inner.string,
                                       // field_infos: inner.field_infos,
         }
@@ -237,12 +814,22 @@ impl<P: EventProvider> ForestFireSubscriber<P> {
     /// Returns the number of logged nodes.
     /// 
     /// Because this works on shared references, it will have to acquire a lock
-    /// on the subscriber state; if you have exclusive reference, prefer
-    /// [`node_count_ex`] instead.
+    /// on the subscriber state (and on the buffer of every thread, if events are
+    /// [buffered](ForestFireSubscriber#recording-events)); if you have exclusive
+    /// reference, prefer [`node_count_ex`] instead.
     /// 
     /// [`node_count_ex`]: ForestFireSubscriber::node_count_ex
     pub fn node_count(&self) -> usize {
-        self.inner().forest.node_count()
+        let count = self.inner().forest.node_count();
+        if !self.buffering.load(Ordering::Relaxed) {
+            return count;
+        }
+        count
+            + self
+                .stack
+                .iter()
+                .map(|local| buffered_count(&mutex_lock_ignore_poison(local)))
+                .sum::<usize>()
     }
 
     /// Returns the number of logged nodes without locking.
@@ -252,22 +839,100 @@ impl<P: EventProvider> ForestFireSubscriber<P> {
     /// 
     /// [`node_count`]: ForestFireSubscriber::node_count
     pub fn node_count_ex(&mut self) -> usize {
-        mutex_get_mut_ignore_poison(&mut self.inner).forest.node_count()
+        let count = mutex_get_mut_ignore_poison(&mut self.inner)
+            .forest
+            .node_count();
+        count
+            + self
+                .stack
+                .iter_mut()
+                .map(|local| buffered_count(mutex_get_mut_ignore_poison(local)))
+                .sum::<usize>()
     }
 
-    fn local<'this>(&'this self) -> MutexGuard<'this, Local> {
-        let mut local = mutex_lock_ignore_poison(self.stack.get_or_default());
-        let current = thread::current().id();
-        if local.last_using_thread != current {
-            local.last_using_thread = current;
-            local.stack.clear();
-        }
+    // locks `inner` when called for the first time on a thread, so it must not be held
+    fn local<'this>(&'this self) -> MutexGuard<'this, Local<P>> {
+        let local = self.stack.get_or(|| {
+            let fork = self.inner().fork();
+            if fork.is_some() {
+                self.buffering.store(true, Ordering::Relaxed);
+            }
+            Mutex::new(Local::new(fork))
+        });
+        let mut local = mutex_lock_ignore_poison(local);
+        local.claim();
         local
     }
 
     fn inner<'this>(&'this self) -> MutexGuard<'this, Inner<P>> {
         mutex_lock_ignore_poison(&self.inner)
     }
+
+    /// Locks `inner`, after placing the events buffered by the current thread into the tree
+    /// so that they stay in order with any node it is about to create.
+    ///
+    /// The current thread's `Local` is locked before `inner`, so `inner` must never be held
+    /// while locking a `Local`.
+    fn inner_flushed<'this>(&'this self) -> MutexGuard<'this, Inner<P>> {
+        if !self.buffering.load(Ordering::Relaxed) {
+            return self.inner();
+        }
+        let mut local = self.local();
+        let mut inner = self.inner();
+        if let Some(fork) = &mut local.fork {
+            inner.merge_fork(fork);
+        }
+        inner
+    }
+
+    /// Records a contextual or root event into the current thread's buffer, returning
+    /// `false` if the provider can't be forked.
+    fn buffer_event(&self, event: &tracing::Event<'_>) -> bool {
+        let mut local = self.local();
+        let local = &mut *local;
+        let Some(fork) = &mut local.fork else {
+            return false;
+        };
+        let Buffer {
+            provider: fork,
+            events,
+        } = fork.buffer.get_mut();
+        let parent = match local.stack.last() {
+            Some(&parent) if event.is_contextual() => parent,
+            _ => fire::BranchId::ROOT,
+        };
+        let thread = thread::current();
+        let mut payload = fork.make_event(
+            usize::MAX,
+            EventInfo {
+                is_span: false,
+                fields: Fields::Iter(event.fields()),
+                metadata: event.metadata(),
+                values_early: None,
+                thread: &thread,
+            },
+        );
+        if P::should_use_visitor() {
+            event.record(&mut fork.make_visitor(usize::MAX, &mut payload));
+        }
+        events.push((parent, payload));
+        true
+    }
+
+    /// Returns the branch a new span or event should be placed into.
+    ///
+    /// `explicit` and `is_contextual` come from the span's attributes or the event.
+    fn parent_of(&self, explicit: Option<&span::Id>, is_contextual: bool) -> fire::BranchId {
+        match explicit {
+            Some(span) => sp2br(span),
+            None if is_contextual => {
+                let local = self.local();
+                local.stack.last().copied().unwrap_or(fire::BranchId::ROOT)
+            }
+            // explicitly a root
+            None => fire::BranchId::ROOT,
+        }
+    }
 }
 
 impl<P: EventProvider + Default> Default for ForestFireSubscriber<P> {
@@ -306,25 +971,61 @@ fn sp2br(span: &span::Id) -> fire::BranchId {
     fire::BranchId::new(v)
 }
 
-fn ensure_normal<T>(forest: &ForestFire<T>, original: &span::Id, id: fire::BranchId) {
+/// Returns `false` if the span's node was evicted by the ring buffer.
+fn ensure_normal<T>(forest: &ForestFire<T>, original: &span::Id, id: fire::BranchId) -> bool {
+    if forest.was_removed(id) {
+        return false;
+    }
     if !forest.exists(id) {
         panic!("the provided span ({}) does not refer to an existing node", original.into_u64())
     }
     if id.is_root() {
         panic!("the provided span ({}) refers to root", original.into_u64());
     }
+    true
 }
 
 impl<P: EventProvider> Subscriber for ForestFireSubscriber<P> {
-    fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
-        // todo?
-        true
+    fn register_callsite(&self, metadata: &'static tracing::Metadata<'static>) -> Interest {
+        match &self.filter {
+            Some(filter) => filter.interest(metadata),
+            None => Interest::always(),
+        }
+    }
+
+    fn enabled(&self, metadata: &tracing::Metadata<'_>) -> bool {
+        let Some(filter) = &self.filter else {
+            return true;
+        };
+        if !filter.has_span_directives() {
+            return filter.enabled_statically(metadata);
+        }
+        if panic::is_panic(metadata) && Inside::is_set() {
+            return false;
+        }
+        let _inside = Inside::enter();
+        let scope = self.local().scope();
+        filter.enabled(metadata, scope)
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        self.filter.as_ref().map(Filter::max_level)
     }
 
     fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
-        let local = self.local();
-        let mut inner = self.inner();
-        let parent = local.stack.last().copied().unwrap_or(fire::BranchId::ROOT);
+        let _inside = Inside::enter();
+        let parent = self.parent_of(span.parent(), span.is_contextual());
+        let thread = thread::current();
+        let mut inner = self.inner_flushed();
+        if let Some(explicit) = span.parent()
+            && !Inner::<P>::is_dropped(parent)
+        {
+            ensure_normal(&inner.forest, explicit, parent);
+        }
+        let parent = match inner.admit(parent, span.metadata()) {
+            Ok(parent) => parent,
+            Err(ancestor) => return br2sp(inner.drop_span(ancestor, span.metadata())),
+        };
         let id = inner.forest.next_id();
         let event = inner.provider.make_event(
             id.value(),
@@ -333,6 +1034,7 @@ impl<P: EventProvider> Subscriber for ForestFireSubscriber<P> {
                 fields: Fields::Full(span.fields()),
                 metadata: span.metadata(),
                 values_early: Some(span.values()),
+                thread: &thread,
             },
         );
         inner.forest.branch(parent, event);
@@ -344,6 +1046,12 @@ impl<P: EventProvider> Subscriber for ForestFireSubscriber<P> {
             span.values()
                 .record(&mut inner.provider.make_visitor(id.value(), payload));
         }
+        inner.span_refs.insert(id, 1);
+        inner.span_metadata.insert(id, span.metadata());
+        if let Some(scope) = self.filter.as_ref().and_then(|f| f.span_scope(span.metadata())) {
+            inner.span_scopes.insert(id, scope);
+        }
+        inner.node_created(id, parent);
 
         br2sp(id)
     }
@@ -354,9 +1062,15 @@ impl<P: EventProvider> Subscriber for ForestFireSubscriber<P> {
         }
 
         let id = sp2br(span);
+        if Inner::<P>::is_dropped(id) {
+            return;
+        }
+        let _inside = Inside::enter();
         let mut inner = self.inner();
         let inner = &mut *inner;
-        ensure_normal(&inner.forest, span, id);
+        if !ensure_normal(&inner.forest, span, id) {
+            return;
+        }
         let payload = inner.forest.payload_mut(id);
         values.record(&mut inner.provider.make_visitor(id.value(), payload));
 
@@ -364,69 +1078,156 @@ impl<P: EventProvider> Subscriber for ForestFireSubscriber<P> {
         // values.record(&mut inner.visitor(fields));
     }
 
-    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {
-        // todo
-    }
+    fn record_follows_from(&self, span: &span::Id, follows: &span::Id) {
+        if !P::should_span_follows_from() {
+            return;
+        }
 
-    fn event(&self, event: &tracing::Event<'_>) {
-        let local = self.local();
+        let id = sp2br(span);
+        let follows_id = sp2br(follows);
+        if Inner::<P>::is_dropped(id) || Inner::<P>::is_dropped(follows_id) {
+            return;
+        }
+        let _inside = Inside::enter();
         let mut inner = self.inner();
-        let id = inner.forest.next_id();
-        let payload = inner.provider.make_event(
-            id.value(),
-            EventInfo {
-                is_span: false,
-                fields: Fields::Iter(event.fields()),
-                metadata: event.metadata(),
-                values_early: None,
-            },
-        );
-        let parent = local.stack.last().copied().unwrap_or(fire::BranchId::ROOT);
-        inner.forest.branch(parent, payload);
-        // let (_, fields) = add(
-        //     &mut inner,
-        //     parent,
-        //     Err(event.fields()),
-        //     event.metadata(),
-        //     false,
-        // );
-        if P::should_use_visitor() {
-            let inner = &mut *inner;
-            let payload = inner.forest.payload_mut(id);
+        let inner = &mut *inner;
+        if !ensure_normal(&inner.forest, span, id)
+            || !ensure_normal(&inner.forest, follows, follows_id)
+        {
+            return;
+        }
+        let payload = inner.forest.payload_mut(id);
+        inner
+            .provider
+            .span_follows_from(id.value(), payload, follows_id.value());
+    }
 
-            event.record(&mut inner.provider.make_visitor(id.value(), payload));
+    fn event(&self, event: &tracing::Event<'_>) {
+        let is_panic = panic::is_panic(event.metadata());
+        if is_panic && Inside::is_set() {
+            return;
         }
-        // event.record(&mut inner.visitor(fields));
+        let _inside = Inside::enter();
+        // panics go into the tree right away, since the process might not survive them
+        if !is_panic && event.parent().is_none() && self.buffer_event(event) {
+            return;
+        }
+        let parent = self.parent_of(event.parent(), event.is_contextual());
+        self.inner_flushed().record_event(parent, event);
     }
 
     fn enter(&self, span: &span::Id) {
+        let _inside = Inside::enter();
         let br = sp2br(span);
+        let scope = if self.filter.as_ref().is_some_and(Filter::has_span_directives) {
+            self.inner().span_scopes.get(&br).copied()
+        } else {
+            None
+        };
         let mut local = self.local();
         local.stack.push(br);
+        if let Some(scope) = scope {
+            local.scopes.push((br, scope));
+        }
         drop(local);
-        if P::should_span_enter() {
+        if P::should_span_enter() && !Inner::<P>::is_dropped(br) {
             let mut inner = self.inner();
-            ensure_normal(&inner.forest, span, br);
+            let inner = &mut *inner;
+            if !ensure_normal(&inner.forest, span, br) {
+                return;
+            }
             let payload = inner.forest.payload_mut(br);
-            self.inner().provider.span_enter(br.value(), payload);
+            inner.provider.span_enter(br.value(), payload);
         }
     }
 
     fn exit(&self, span: &span::Id) {
+        let _inside = Inside::enter();
         let br = sp2br(span);
         let mut local = self.local();
-        // todo: is this correct behaviour?
-        while let Some(removed) = local.stack.pop() {
-            if removed == br {
-                break;
+        let current = local.last_using_thread;
+        let exited_here = local.exit(br);
+        drop(local);
+        if !exited_here {
+            // entered on another thread. only one `Local` is locked at a time,
+            // so threads doing this at once can't deadlock
+            for other in self.stack.iter() {
+                let mut other = mutex_lock_ignore_poison(other);
+                if other.last_using_thread != current && other.exit(br) {
+                    break;
+                }
             }
         }
-        drop(local);
-        if P::should_span_exit() {
+        if P::should_span_exit() && !Inner::<P>::is_dropped(br) {
             let mut inner = self.inner();
-            ensure_normal(&inner.forest, span, br);
+            let inner = &mut *inner;
+            if !ensure_normal(&inner.forest, span, br) {
+                return;
+            }
             let payload = inner.forest.payload_mut(br);
-            self.inner().provider.span_exit(br.value(), payload);
+            inner
+                .provider
+                .span_exit(br.value(), payload, thread::panicking());
+        }
+    }
+
+    fn clone_span(&self, span: &span::Id) -> span::Id {
+        let _inside = Inside::enter();
+        let br = sp2br(span);
+        let mut inner = self.inner();
+        if !Inner::<P>::is_dropped(br) {
+            ensure_normal(&inner.forest, span, br);
+        }
+        // spans which have already been closed are not revived
+        if let Some(refs) = inner.span_refs.get_mut(&br) {
+            *refs += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: span::Id) -> bool {
+        let _inside = Inside::enter();
+        let br = sp2br(&span);
+        let mut inner = self.inner();
+        let inner = &mut *inner;
+        let dropped = Inner::<P>::is_dropped(br);
+        let evicted = !dropped && !ensure_normal(&inner.forest, &span, br);
+        let Some(refs) = inner.span_refs.get_mut(&br) else {
+            return false;
+        };
+        *refs -= 1;
+        if *refs != 0 {
+            return false;
+        }
+
+        inner.span_refs.remove(&br);
+        inner.span_metadata.remove(&br);
+        inner.span_scopes.remove(&br);
+        if dropped {
+            inner.dropped_spans.remove(&br);
+        } else if !evicted {
+            if P::should_span_close() {
+                let payload = inner.forest.payload_mut(br);
+                inner
+                    .provider
+                    .span_close(br.value(), payload, thread::panicking());
+            }
+            if inner.forest.parent(br) == Some(fire::BranchId::ROOT) {
+                inner.subtree_completed(br);
+            }
+        }
+        true
+    }
+
+    fn current_span(&self) -> Current {
+        let _inside = Inside::enter();
+        let Some(br) = self.local().stack.last().copied() else {
+            return Current::none();
+        };
+        match self.inner().span_metadata.get(&br) {
+            Some(metadata) => Current::new(br2sp(br), metadata),
+            // only closed spans are missing, which can't be entered anymore
+            None => Current::none(),
         }
     }
 }
@@ -441,6 +1242,10 @@ pub struct AshTrayce<P: EventProvider> {
     pub ash: Ashes<P::Event>,
     /// The event provider used to create the `ForestFireSubscriber`.
     pub provider: P,
+    /// The nodes which were dropped because the [budget] ran out.
+    ///
+    /// [budget]: ForestFireSubscriber::with_budget
+    pub dropped: DroppedNodes,
 }
 
 /// Runs a function with a [`ForestFireSubscriber`] using the given [`EventProvider`],
@@ -470,7 +1275,28 @@ where
     Provider::Event: Send,
 {
     let fire = ForestFireSubscriber::new(ForestFire::default(), provider);
-    let fire = Arc::new(fire);
+    nothread_run_subscriber_ret(fire, func)
+}
+
+/// Runs a function with an already constructed [`ForestFireSubscriber`], allowing returning
+/// values.
+///
+/// This is the same as [`nothread_run_forest_ret`], except that the subscriber can be
+/// configured beforehand (for example, to [stream] its nodes).
+///
+/// [stream]: ForestFireSubscriber::with_stream
+///
+/// # Panics
+///
+/// See [`nothread_run_forest_ret`](nothread_run_forest_ret#panics).
+pub fn nothread_run_subscriber_ret<Provider: EventProvider + Send, R>(
+    subscriber: ForestFireSubscriber<Provider>,
+    func: impl FnOnce() -> R,
+) -> (R, AshTrayce<Provider>)
+where
+    Provider::Event: Send,
+{
+    let fire = Arc::new(subscriber);
     let out = tracing::subscriber::with_default(Arc::clone(&fire), func);
     let fire = Arc::into_inner(fire).unwrap_or_else(|| panic!("forest fire escaped"));
     let ash = fire.burn();
@@ -483,13 +1309,13 @@ where
 /// [default trace subscriber] for the current thread; any uses of the default trace
 /// macros will report to this subscriber.
 ///
-/// If you'd like to return a value from the closure, use [`run_forest_ret`].
+/// If you'd like to return a value from the closure, use [`nothread_run_forest_ret`].
 ///
 /// [default trace subscriber]: tracing::subscriber::with_default
 ///
 /// # Panics
 ///
-/// See [`run_forest_ret`](run_forest_ret#panics)
+/// See [`nothread_run_forest_ret`](nothread_run_forest_ret#panics)
 pub fn nothread_local_run_forest<Provider: EventProvider + Send>(
     provider: Provider,
     func: impl FnOnce(),
@@ -501,6 +1327,236 @@ where
     trayce
 }
 
+/// A handle for spawning threads which record into the same [`ForestFireSubscriber`], given
+/// by [`run_forest_ret`] and friends.
+///
+/// This wraps a [`thread::Scope`]; see [`ForestScope::spawn`].
+pub struct ForestScope<'scope, 'env: 'scope, P: EventProvider> {
+    scope: &'scope thread::Scope<'scope, 'env>,
+    dispatch: Dispatch,
+    subscriber: Arc<ForestFireSubscriber<P>>,
+}
+
+impl<'scope, 'env, P> ForestScope<'scope, 'env, P>
+where
+    P: EventProvider + Send,
+    P::Event: Send,
+{
+    /// Spawns a scoped thread which records into the same tree as the current thread.
+    ///
+    /// The new thread uses the forest's subscriber as its [default trace subscriber], and
+    /// anything recorded in it is placed inside of the span which is current on the calling
+    /// thread (as if that span was entered on the new thread). The span is kept open
+    /// until the new thread finishes.
+    ///
+    /// `func` is given a `ForestScope` of its own, through which it can spawn more threads.
+    ///
+    /// See [`thread::Scope::spawn`] for more details.
+    ///
+    /// [default trace subscriber]: tracing::dispatcher::with_default
+    pub fn spawn<F, T>(&self, func: F) -> thread::ScopedJoinHandle<'scope, T>
+    where
+        F: FnOnce(&ForestScope<'scope, 'env, P>) -> T + Send + 'scope,
+        T: Send + 'scope,
+    {
+        let parent = self.subscriber.local().stack.last().copied();
+        // keep the parent open for as long as the thread is using it
+        let parent = parent.map(|parent| self.subscriber.clone_span(&br2sp(parent)));
+        let forest_scope = ForestScope {
+            scope: self.scope,
+            dispatch: self.dispatch.clone(),
+            subscriber: Arc::clone(&self.subscriber),
+        };
+
+        self.scope.spawn(move || {
+            struct Guard<'a, P: EventProvider> {
+                subscriber: &'a ForestFireSubscriber<P>,
+                parent: Option<span::Id>,
+            }
+            impl<'a, P: EventProvider> Drop for Guard<'a, P> {
+                fn drop(&mut self) {
+                    let _inside = Inside::enter();
+                    // the thread is done, so its buffered events can be placed already
+                    drop(self.subscriber.inner_flushed());
+                    if let Some(parent) = self.parent.take() {
+                        self.subscriber.local().stack.clear();
+                        self.subscriber.try_close(parent);
+                    }
+                }
+            }
+
+            if let Some(parent) = &parent {
+                forest_scope.subscriber.local().stack.push(sp2br(parent));
+            }
+            let _guard = Guard {
+                subscriber: &forest_scope.subscriber,
+                parent,
+            };
+            tracing::dispatcher::with_default(&forest_scope.dispatch, || func(&forest_scope))
+        })
+    }
+
+    /// Returns the underlying [`thread::Scope`].
+    ///
+    /// Threads spawned directly through it do not record into the tree, unless they set
+    /// the default subscriber themselves.
+    pub fn thread_scope(&self) -> &'scope thread::Scope<'scope, 'env> {
+        self.scope
+    }
+}
+
+impl<'scope, 'env, P: EventProvider> fmt::Debug for ForestScope<'scope, 'env, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ForestScope").finish_non_exhaustive()
+    }
+}
+
+/// Runs a function with a [`ForestFireSubscriber`] using the given [`EventProvider`],
+/// allowing it to spawn threads which record into the same tree and returning values.
+///
+/// This is like [`nothread_run_forest_ret`], except that `func` is run inside of a
+/// [`thread::scope`] and can spawn threads through the given [`ForestScope`]. Once every
+/// spawned thread has finished, everything is burned into a single [`AshTrayce`].
+///
+/// If you're not returning any values, you'll probably want [`run_forest`].
+///
+/// # Example
+///
+/// ```
+/// use forrust_fire_tracing::{providers::log::LogEventProvider, run_forest_ret};
+///
+/// let numbers = [1, 2, 3, 4];
+/// let (sum, ash_trayce) = run_forest_ret(LogEventProvider::new(), |scope| {
+///     let _span = tracing::info_span!("summing").entered();
+///     let handles: Vec<_> = numbers
+///         .iter()
+///         .map(|i| {
+///             scope.spawn(move |_| {
+///                 tracing::info!(i, "adding");
+///                 i
+///             })
+///         })
+///         .collect();
+///     handles.into_iter().map(|h| h.join().unwrap()).sum::<i32>()
+/// });
+/// assert_eq!(sum, 10);
+///
+/// // every event was placed inside of the span
+/// let summing = ash_trayce.ash.branch(ash_trayce.ash.root().child(0));
+/// assert_eq!(summing.n_children(), 4);
+/// ```
+///
+/// # Panics
+///
+/// Panics if any of the spawned threads panicked and their handle was not joined, just like
+/// [`thread::scope`].
+///
+/// See also [`nothread_run_forest_ret`](nothread_run_forest_ret#panics).
+pub fn run_forest_ret<'env, Provider, R>(
+    provider: Provider,
+    func: impl for<'scope> FnOnce(&ForestScope<'scope, 'env, Provider>) -> R,
+) -> (R, AshTrayce<Provider>)
+where
+    Provider: EventProvider + Send,
+    Provider::Event: Send,
+{
+    let fire = ForestFireSubscriber::new(ForestFire::default(), provider);
+    run_subscriber_ret(fire, func)
+}
+
+/// Runs a function with an already constructed [`ForestFireSubscriber`], allowing it to
+/// spawn threads which record into the same tree and returning values.
+///
+/// This is the same as [`run_forest_ret`], except that the subscriber can be configured
+/// beforehand.
+///
+/// # Panics
+///
+/// See [`run_forest_ret`](run_forest_ret#panics).
+pub fn run_subscriber_ret<'env, Provider, R>(
+    subscriber: ForestFireSubscriber<Provider>,
+    func: impl for<'scope> FnOnce(&ForestScope<'scope, 'env, Provider>) -> R,
+) -> (R, AshTrayce<Provider>)
+where
+    Provider: EventProvider + Send,
+    Provider::Event: Send,
+{
+    let fire = Arc::new(subscriber);
+    let dispatch = Dispatch::from(Arc::clone(&fire));
+    let out = thread::scope(|scope| {
+        let forest_scope = ForestScope {
+            scope,
+            dispatch,
+            subscriber: Arc::clone(&fire),
+        };
+        tracing::dispatcher::with_default(&forest_scope.dispatch, || func(&forest_scope))
+    });
+    let fire = Arc::into_inner(fire).unwrap_or_else(|| panic!("forest fire escaped"));
+    let ash = fire.burn();
+    (out, ash)
+}
+
+/// Runs a function with a [`ForestFireSubscriber`] using the given [`EventProvider`],
+/// allowing it to spawn threads which record into the same tree.
+///
+/// If you'd like to return a value from the closure, use [`run_forest_ret`].
+///
+/// # Panics
+///
+/// See [`run_forest_ret`](run_forest_ret#panics).
+pub fn run_forest<'env, Provider>(
+    provider: Provider,
+    func: impl for<'scope> FnOnce(&ForestScope<'scope, 'env, Provider>),
+) -> AshTrayce<Provider>
+where
+    Provider: EventProvider + Send,
+    Provider::Event: Send,
+{
+    let ((), trayce) = run_forest_ret(provider, func);
+    trayce
+}
+
+fn buffered_count<P: EventProvider>(local: &Local<P>) -> usize {
+    local
+        .fork
+        .as_ref()
+        .map_or(0, |fork| fork.buffer.get().events.len())
+}
+
+thread_local! {
+    // whether the current thread is running a subscriber's code, see `Inside`
+    static INSIDE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks the current thread as running the code of a subscriber from this crate, and so
+/// possibly holding its locks, until dropped.
+///
+/// An event from a [`PanicHook`] recorded while this is set comes from a panic inside of
+/// the subscriber, so unlike other events, it can't wait for the locks to be released.
+///
+/// [`PanicHook`]: panic::PanicHook
+pub(crate) struct Inside {
+    was_inside: bool,
+}
+
+impl Inside {
+    pub(crate) fn enter() -> Self {
+        Self {
+            was_inside: INSIDE.replace(true),
+        }
+    }
+
+    pub(crate) fn is_set() -> bool {
+        INSIDE.get()
+    }
+}
+
+impl Drop for Inside {
+    fn drop(&mut self) {
+        INSIDE.set(self.was_inside);
+    }
+}
+
 fn mutex_get_mut_ignore_poison<T>(mutex: &mut Mutex<T>) -> &mut T {
     match mutex.get_mut() {
         Ok(r) => r,
@@ -0,0 +1,99 @@
+//! Recording panics into the tree.
+//!
+//! By default, a panic leaves no trace in the tree: only the spans it unwinds through are
+//! exited. Installing a [`PanicHook`] records every panic as an event, placed into the
+//! span which is current on the panicking thread:
+//!
+//! ```
+//! use forrust_fire_tracing::{panic::PanicHook, providers::{ProviderExt, log::LogEventProvider}};
+//!
+//! PanicHook::new().install();
+//!
+//! let log_ashes = LogEventProvider::new().run(|| {
+//!     let _span = tracing::info_span!("risky").entered();
+//!     let _ = std::panic::catch_unwind(|| panic!("oh no"));
+//! });
+//!
+//! let ash = &log_ashes.ash;
+//! let span = ash.root().child(0);
+//! let panic = ash.branch(span).child(0);
+//! assert_eq!(ash.branch(panic).payload().unwrap().metadata.target(), "panic");
+//! ```
+//!
+//! The event goes to whichever subscriber is the default on the panicking thread, so it
+//! works with the [layer](crate::layer) as well. Like any other event, it records the
+//! thread which created it.
+
+use std::{backtrace::Backtrace, cell::Cell, panic::PanicHookInfo};
+
+/// The target of events recorded by a [`PanicHook`].
+pub const PANIC_TARGET: &str = "panic";
+
+/// A panic hook which records panics as `ERROR` events with the [`PANIC_TARGET`] target.
+///
+/// The event's message is the panic message, and its `location` field holds the file, line
+/// and column the panic came from. If enabled, the `backtrace` field holds a backtrace.
+///
+/// Panics which happen while the current thread is recording into a subscriber from this
+/// crate (such as panics from an [`EventProvider`]) can't be recorded, since the thread may
+/// be holding the subscriber's locks. Any other panic is recorded, waiting for the locks if
+/// other threads hold them.
+///
+/// [`EventProvider`]: crate::EventProvider
+#[derive(Debug, Clone, Default)]
+pub struct PanicHook {
+    backtrace: bool,
+}
+
+impl PanicHook {
+    /// Creates a new panic hook which does not capture backtraces.
+    pub const fn new() -> Self {
+        Self { backtrace: false }
+    }
+
+    /// Sets whether a backtrace is captured for every panic, regardless of the
+    /// `RUST_BACKTRACE` environment variable.
+    pub const fn backtrace(mut self, backtrace: bool) -> Self {
+        self.backtrace = backtrace;
+        self
+    }
+
+    /// Installs this hook, replacing the current one (see [`std::panic::set_hook`]).
+    ///
+    /// Every panic is passed on to the replaced hook after being recorded, so the default
+    /// hook still prints it. Installing a `PanicHook` more than once records every panic
+    /// more than once.
+    pub fn install(self) {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            self.record(info);
+            previous(info);
+        }));
+    }
+
+    /// Records `info` as an event.
+    pub fn record(&self, info: &PanicHookInfo<'_>) {
+        let message = info.payload_as_str().unwrap_or("Box<dyn Any>");
+        let location = info.location().map(tracing::field::display);
+        let backtrace = self.backtrace.then(Backtrace::force_capture);
+        let was_recording = RECORDING.replace(true);
+        tracing::error!(
+            target: PANIC_TARGET,
+            location,
+            backtrace = backtrace.as_ref().map(tracing::field::display),
+            "{message}",
+        );
+        RECORDING.set(was_recording);
+    }
+}
+
+thread_local! {
+    // set while a `PanicHook` records a panic on this thread, so that its event can't be
+    // mixed up with other events which happen to use the same target
+    static RECORDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Returns whether an event comes from a [`PanicHook`].
+pub(crate) fn is_panic(metadata: &tracing::Metadata<'_>) -> bool {
+    RECORDING.get() && metadata.target() == PANIC_TARGET
+}
@@ -20,7 +20,9 @@ pub trait ProviderExt: EventProvider {
     ///
     /// This is just a shorthand for [`nothread_run_forest_ret`]. Just like `nothread_run_forest_ret`,
     /// the subscriber is **only set for the current thread**; if you'd like to multithread,
-    /// consider a manual approach.
+    /// see [`run_forest_ret`].
+    ///
+    /// [`run_forest_ret`]: crate::run_forest_ret
     ///
     /// [`ForestFireSubscriber`]: crate::ForestFireSubscriber
     ///
@@ -38,12 +40,13 @@ pub trait ProviderExt: EventProvider {
 
     /// Runs a function with a [`ForestFireSubscriber`] using the given [`EventProvider`].
     ///
-    /// This is just a shorthand for [`nothread_run_forest`]. Just like `nothread_run_forest`,
-    /// the subscriber is **only set for the current thread**; if you'd like to multithread,
-    /// consider a manual approach.
+    /// This is just a shorthand for [`nothread_local_run_forest`]. Just like
+    /// `nothread_local_run_forest`, the subscriber is **only set for the current thread**; if
+    /// you'd like to multithread, see [`run_forest`].
     ///
     /// [`ForestFireSubscriber`]: crate::ForestFireSubscriber
-    /// [`nothread_run_forest`]: crate::run_forest
+    /// [`nothread_local_run_forest`]: crate::nothread_local_run_forest
+    /// [`run_forest`]: crate::run_forest
     ///
     /// # Panics
     ///
@@ -2,11 +2,17 @@
 //!
 //! The main point of interest here is [`LogEventProvider`].
 
-use std::{fmt::Write as _, ops::Range};
+use std::{
+    fmt::Write as _,
+    mem,
+    ops::Range,
+    thread::ThreadId,
+    time::{Duration, Instant},
+};
 
 use tracing::field;
 
-use crate::{AshTrayce, EventInfo, EventProvider, Fields};
+use crate::{AshTrayce, EventInfo, EventProvider, Fields, Fork};
 
 /// The finished-up and traversable tree returned by [`ForestFireSubscriber::burn`].
 ///
@@ -19,50 +25,139 @@ pub type LogAshes = AshTrayce<LogEventProvider>;
 #[derive(Debug)]
 #[non_exhaustive]
 pub struct FieldInfo {
-    // value will never naturally reach usize::MAX
-    // because strings can be at most isize::MAX
-    // bytes long
-    /// The range of bytes within the [content string] which house the string
-    /// representation of the field's value.
-    ///
-    /// If the field value has not yet been provided, this field will be set to
-    /// `usize::MAX..usize::MAX`. The checking can be handled automatically for
-    /// you by [`FieldInfo::value()`].
-    ///
-    /// [content string]: LogEventProvider::string
-    pub value: Range<usize>,
+    /// The field's value, or `None` if it has not yet been provided.
+    pub value: Option<FieldValue>,
     /// The field's name.
     pub name: &'static str,
 }
 
 impl FieldInfo {
-    /// The range of bytes within the [content string] which house the string
-    /// representation of the field's value, or `None` if it is not yet
-    /// initialized.
+    /// Extracts the subslice of the given string which holds the field's value, or `None`
+    /// if the field has not been filled out or its value is not a string.
+    ///
+    /// See [`FieldValue::as_str`].
     ///
-    /// The range can be retrieved manually without checking for absence using
-    /// the `value` field.
+    /// # Panics
+    ///
+    /// Panics if the value's range cannot be used to index the given string (be it due
+    /// to char boundaries or due to out-of-range access), which can easily happen if the
+    /// given string is not the same one which was used for recording this field.
+    pub fn get_value<'s>(&self, str: &'s str) -> Option<&'s str> {
+        self.value.as_ref().and_then(|value| value.as_str(str))
+    }
+}
+
+/// The value of a field, keeping the type it was recorded with.
+///
+/// Strings and bytes are not stored inline; they are ranges within the [content string]
+/// and [`LogEventProvider::bytes`] respectively.
+///
+/// [content string]: LogEventProvider::string
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    /// A signed integer.
+    I64(i64),
+    /// An unsigned integer.
+    U64(u64),
+    /// A signed 128-bit integer.
+    I128(i128),
+    /// An unsigned 128-bit integer.
+    U128(u128),
+    /// A floating point number.
+    F64(f64),
+    /// A boolean.
+    Bool(bool),
+    /// A string, as a range within the [content string].
     ///
     /// [content string]: LogEventProvider::string
-    pub fn value(&self) -> Option<Range<usize>> {
-        if self.value == (usize::MAX..usize::MAX) {
-            None
-        } else {
-            Some(Range::clone(&self.value))
+    Str(Range<usize>),
+    /// A byte string, as a range within [`LogEventProvider::bytes`].
+    Bytes(Range<usize>),
+    /// Any other value, formatted with [`Debug`](std::fmt::Debug), as a range within the
+    /// [content string].
+    ///
+    /// [content string]: LogEventProvider::string
+    Debug(Range<usize>),
+    /// An error recorded through [`record_error`], followed by its [sources], as a range
+    /// within [`LogEventProvider::errors`].
+    ///
+    /// [`record_error`]: field::Visit::record_error
+    /// [sources]: std::error::Error::source
+    Error(Range<usize>),
+}
+
+impl FieldValue {
+    /// Extracts the subslice of the given string which holds this value, or `None` if it
+    /// is not a [`Str`](FieldValue::Str) or [`Debug`](FieldValue::Debug) value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range cannot be used to index the given string.
+    pub fn as_str<'s>(&self, string: &'s str) -> Option<&'s str> {
+        self.string_range().map(|range| &string[range])
+    }
+
+    /// Extracts the subslice of the given bytes which holds this value, or `None` if it
+    /// is not a [`Bytes`](FieldValue::Bytes) value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range cannot be used to index the given bytes.
+    pub fn as_bytes<'b>(&self, bytes: &'b [u8]) -> Option<&'b [u8]> {
+        match self {
+            Self::Bytes(range) => Some(&bytes[Range::clone(range)]),
+            _ => None,
         }
     }
 
-    /// Extracts the subslice of the given string which represents the field's
-    /// value, or `None` if the field has not been filled out.
+    /// Returns the [`Display`](std::fmt::Display) string of the error and each of its
+    /// sources, or `None` if this is not an [`Error`](FieldValue::Error) value.
     ///
     /// # Panics
     ///
-    /// Panics if this struct's `value` field cannot be used to index the given
-    /// string (be it due to char boundaries or due to out-of-range access),
-    /// which can easily happen if the given string is not the same one which
-    /// was used for recording this field.
-    pub fn get_value<'s>(&self, str: &'s str) -> Option<&'s str> {
-        self.value().map(|range| &str[range])
+    /// Panics if the ranges cannot be used to index the given errors and string.
+    pub fn as_error_chain<'s>(
+        &self,
+        errors: &'s [Range<usize>],
+        string: &'s str,
+    ) -> Option<impl Iterator<Item = &'s str> + 's> {
+        match self {
+            Self::Error(range) => Some(
+                errors[Range::clone(range)]
+                    .iter()
+                    .map(|error| &string[Range::clone(error)]),
+            ),
+            _ => None,
+        }
+    }
+
+    fn string_range(&self) -> Option<Range<usize>> {
+        match self {
+            Self::Str(range) | Self::Debug(range) => Some(Range::clone(range)),
+            _ => None,
+        }
+    }
+
+    /// Returns how many bytes of the content string and bytes this value takes up.
+    fn stored_len(&self, errors: &[Range<usize>]) -> usize {
+        match self {
+            Self::Str(range) | Self::Bytes(range) | Self::Debug(range) => range.len(),
+            Self::Error(range) => errors[Range::clone(range)].iter().map(Range::len).sum(),
+            _ => 0,
+        }
+    }
+
+    /// Moves this value's range within the content string, bytes or errors by the given
+    /// offsets.
+    fn offset(self, string_offset: usize, bytes_offset: usize, errors_offset: usize) -> Self {
+        match self {
+            Self::Str(range) => Self::Str(offset(range, string_offset)),
+            Self::Debug(range) => Self::Debug(offset(range, string_offset)),
+            Self::Bytes(range) => Self::Bytes(offset(range, bytes_offset)),
+            Self::Error(range) => Self::Error(offset(range, errors_offset)),
+            value => value,
+        }
     }
 }
 
@@ -75,11 +170,156 @@ pub struct LogEvent {
     pub fields: Range<usize>,
     /// The metadata for this event.
     pub metadata: &'static tracing::Metadata<'static>,
+    /// When the event was created.
+    pub created: Instant,
+    /// Timing information of the span, or `None` if this event is not a span.
+    pub span_timing: Option<SpanTiming>,
+    /// The index within [`LogEventProvider::threads`] of the thread which created
+    /// this event.
+    pub thread: usize,
+}
+
+/// A thread which created at least one event.
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct ThreadInfo {
+    /// The thread's ID.
+    pub id: ThreadId,
+    /// The thread's name, if it has one.
+    pub name: Option<String>,
+}
+
+/// Timing information of a span, collected by [`LogEventProvider`].
+///
+/// A span is *busy* while it is entered (on any thread) and *idle* otherwise, starting
+/// from its creation. Time which has not been followed up by an enter, exit or close yet
+/// is not counted: for example, the idle time of a span which is never closed stops
+/// at its last exit.
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct SpanTiming {
+    /// When the span was first entered, or `None` if it never was.
+    pub first_entered: Option<Instant>,
+    /// When the span was last exited, or `None` if it never was.
+    pub last_exited: Option<Instant>,
+    /// When the span was closed, or `None` if it was not closed yet.
+    pub closed: Option<Instant>,
+    /// How long the span was entered for.
+    pub busy: Duration,
+    /// How long the span existed without being entered.
+    pub idle: Duration,
+    /// How many times the span was entered.
+    pub entries: usize,
+    /// Whether the span was exited or closed by a thread [unwinding from a panic], meaning
+    /// that the panic passed through it.
+    ///
+    /// [unwinding from a panic]: std::thread::panicking
+    pub unwound: bool,
+    // the last time `busy` or `idle` were updated
+    last_update: Instant,
+    // how many times the span is currently entered (more than once if it's
+    // entered on multiple threads or recursively)
+    entered: usize,
+}
+
+impl SpanTiming {
+    fn new(created: Instant) -> Self {
+        Self {
+            first_entered: None,
+            last_exited: None,
+            closed: None,
+            busy: Duration::ZERO,
+            idle: Duration::ZERO,
+            entries: 0,
+            unwound: false,
+            last_update: created,
+            entered: 0,
+        }
+    }
+
+    /// Adds the time since the last update to `busy` or `idle`.
+    fn update(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_update);
+        if self.entered == 0 {
+            self.idle += elapsed;
+        } else {
+            self.busy += elapsed;
+        }
+        self.last_update = now;
+    }
+
+    fn enter(&mut self, now: Instant) {
+        self.update(now);
+        self.first_entered.get_or_insert(now);
+        self.entries += 1;
+        self.entered += 1;
+    }
+
+    fn exit(&mut self, now: Instant) {
+        self.update(now);
+        self.last_exited = Some(now);
+        self.entered = self.entered.saturating_sub(1);
+    }
+
+    fn close(&mut self, now: Instant) {
+        self.update(now);
+        self.closed = Some(now);
+    }
 }
 
 /// A built-in implementation of [`EventProvider`] which provides most traced
 /// information you'd want.
 ///
+/// Besides fields and metadata, every event records when it was created, and every
+/// span records its [timing](SpanTiming). When serialized, these are put under the `time`
+/// key of each node, as nanoseconds since the first node was created:
+///
+/// ```json
+/// {"created": 1200, "entered": 1500, "exited": 9000, "closed": 9100, "busy": 7500, "idle": 400, "entries": 2}
+/// ```
+///
+/// `created` is present for every node, the rest only for spans. `entered` is the first
+/// time the span was entered and `exited` the last time it was exited; these and `closed`
+/// are `null` if they haven't happened. `entries` is not a time, but the number of times
+/// the span was entered.
+///
+/// Fields are put under the `ctx` key, keeping the type they were recorded with (see
+/// [`FieldValue`]): integers, floats and bools become JSON numbers and booleans, strings
+/// become strings, and byte strings become arrays of numbers. Anything else becomes the
+/// string of its `Debug` representation, as do 128-bit integers which don't fit into 64
+/// bits. Fields which were never recorded are `null`.
+///
+/// Errors recorded through [`record_error`] become an array of strings: the error's
+/// `Display` representation, followed by that of each of its [sources] in order.
+///
+/// The thread which created each node is put under the `thread` key. Threads are numbered
+/// in the order they first created a node, since [`ThreadId`]s cannot be serialized:
+///
+/// ```json
+/// {"id": 0, "name": "main"}
+/// ```
+///
+/// Every node also gets its node ID under the `id` key. [Follows-from] relationships
+/// between spans are referenced by these IDs: they are serialized under the `follows_from`
+/// key of the root node, mapping the ID of each span to the IDs of the spans it follows
+/// from (the key is left out if there aren't any):
+///
+/// ```json
+/// {"0": {"v": {"id": 0, ...}}, "1": {"v": {"id": 1, ...}}, "follows_from": {"1": [0]}}
+/// ```
+///
+/// Spans which a panic passed through (see [`SpanTiming::unwound`]) get `"unwound": true`;
+/// the key is left out for every other node.
+///
+/// If nodes were dropped because a [budget] ran out, their number is put under the
+/// `dropped` key of the node they would have been placed into (or of the root node), next
+/// to `v`. The key is left out if nothing was dropped there.
+///
+/// [`record_error`]: field::Visit::record_error
+/// [sources]: std::error::Error::source
+/// [Follows-from]: tracing::Span::follows_from
+/// [budget]: crate::budget
+///
 /// # Example
 ///
 /// ```
@@ -115,6 +355,37 @@ pub struct LogEventProvider {
     /// You can find out where to index to find the string for a particular
     /// event by [`FieldInfo::value`] (or use [`FieldInfo::get_value`])
     pub string: String,
+    /// Byte string values of recorded fields.
+    ///
+    /// Indexed by [`FieldValue::Bytes`] (or use [`FieldValue::as_bytes`]).
+    pub bytes: Vec<u8>,
+    /// Recorded errors and their sources, as ranges within the [content string] of their
+    /// [`Display`](std::fmt::Display) representations.
+    ///
+    /// Indexed by [`FieldValue::Error`] (or use [`FieldValue::as_error_chain`]).
+    ///
+    /// [content string]: LogEventProvider::string
+    pub errors: Vec<Range<usize>>,
+    /// The creation time of the first event, or `None` if no events have
+    /// been created yet.
+    ///
+    /// When serialized, all timestamps are relative to this instant.
+    pub epoch: Option<Instant>,
+    /// Recorded [follows-from] relationships, as `(span, follows)` pairs of node IDs
+    /// in the order they were recorded.
+    ///
+    /// [follows-from]: tracing::Span::follows_from
+    pub follows_from: Vec<(usize, usize)>,
+    /// Threads which created events, in the order they created their first one.
+    ///
+    /// Indexed by [`LogEvent::thread`].
+    pub threads: Vec<ThreadInfo>,
+    // parts of `string`, `bytes` and `field_infos` which only belong to removed events,
+    // reclaimed once they make up half of them
+    dead_bytes: usize,
+    dead_fields: usize,
+    // whether to fork for every thread, see `with_buffering`
+    buffering: bool,
 }
 
 impl LogEventProvider {
@@ -123,7 +394,87 @@ impl LogEventProvider {
         Self {
             field_infos: Vec::new(),
             string: String::new(),
+            bytes: Vec::new(),
+            errors: Vec::new(),
+            epoch: None,
+            follows_from: Vec::new(),
+            threads: Vec::new(),
+            dead_bytes: 0,
+            dead_fields: 0,
+            buffering: false,
+        }
+    }
+
+    /// Makes this provider [fork](EventProvider::fork) for every thread, so that events are
+    /// recorded into a buffer of the thread creating them instead of under the subscriber's
+    /// lock.
+    ///
+    /// This is an experimental mode, and off by default. It has not been shown to be
+    /// faster: every event still locks the recording thread's own state, and placing the
+    /// buffered events into the tree later costs about as much as the locking it saves. In
+    /// the `event_throughput` benchmark, it is slower with 1 and 4 threads and on par with
+    /// 16.
+    pub const fn with_buffering(mut self) -> Self {
+        self.buffering = true;
+        self
+    }
+
+    /// Rebuilds the content string, bytes, errors and field infos from the remaining events
+    /// only.
+    fn compact<'a>(&mut self, remaining: impl Iterator<Item = &'a mut LogEvent>) {
+        let mut string = String::new();
+        let mut bytes = Vec::new();
+        let mut errors = Vec::new();
+        let mut field_infos = Vec::with_capacity(self.field_infos.len() - self.dead_fields);
+        for event in remaining {
+            let start = field_infos.len();
+            for info in &self.field_infos[Range::clone(&event.fields)] {
+                let value = info.value.clone().map(|value| match value {
+                    FieldValue::Str(range) => {
+                        FieldValue::Str(push_str(&mut string, &self.string[range]))
+                    }
+                    FieldValue::Debug(range) => {
+                        FieldValue::Debug(push_str(&mut string, &self.string[range]))
+                    }
+                    FieldValue::Bytes(range) => {
+                        let bytes_start = bytes.len();
+                        bytes.extend_from_slice(&self.bytes[range]);
+                        FieldValue::Bytes(bytes_start..bytes.len())
+                    }
+                    FieldValue::Error(range) => {
+                        let errors_start = errors.len();
+                        for error in &self.errors[range] {
+                            errors.push(push_str(&mut string, &self.string[Range::clone(error)]));
+                        }
+                        FieldValue::Error(errors_start..errors.len())
+                    }
+                    value => value,
+                });
+                field_infos.push(FieldInfo {
+                    value,
+                    name: info.name,
+                });
+            }
+            event.fields = start..field_infos.len();
+        }
+        self.string = string;
+        self.bytes = bytes;
+        self.errors = errors;
+        self.field_infos = field_infos;
+        self.dead_bytes = 0;
+        self.dead_fields = 0;
+    }
+
+    fn thread_index(&mut self, thread: &std::thread::Thread) -> usize {
+        // threads usually log in bursts, so the one we're looking for is likely near the end
+        if let Some(index) = self.threads.iter().rposition(|info| info.id == thread.id()) {
+            return index;
         }
+        self.threads.push(ThreadInfo {
+            id: thread.id(),
+            name: thread.name().map(str::to_owned),
+        });
+        self.threads.len() - 1
     }
 
     fn make_visitor_impl(&mut self, event: &mut LogEvent) -> impl tracing::field::Visit {
@@ -131,12 +482,56 @@ impl LogEventProvider {
             p: &'a mut LogEventProvider,
             fields: Range<usize>,
         }
-        impl<'a> field::Visit for V<'a> {
-            fn record_debug(&mut self, field: &field::Field, value: &dyn std::fmt::Debug) {
+        impl<'a> V<'a> {
+            fn set(&mut self, field: &field::Field, value: FieldValue) {
                 if field.index() >= self.fields.len() {
                     return; // simply ignore fields that don't exist
                 }
+                let field_idx = self
+                    .fields
+                    .start
+                    .checked_add(field.index()).expect("the field info should already exist at this index, so the index should also not overflow usize");
+                self.p.field_infos[field_idx].value = Some(value);
+            }
+        }
+        impl<'a> field::Visit for V<'a> {
+            fn record_i64(&mut self, field: &field::Field, value: i64) {
+                self.set(field, FieldValue::I64(value));
+            }
 
+            fn record_u64(&mut self, field: &field::Field, value: u64) {
+                self.set(field, FieldValue::U64(value));
+            }
+
+            fn record_i128(&mut self, field: &field::Field, value: i128) {
+                self.set(field, FieldValue::I128(value));
+            }
+
+            fn record_u128(&mut self, field: &field::Field, value: u128) {
+                self.set(field, FieldValue::U128(value));
+            }
+
+            fn record_f64(&mut self, field: &field::Field, value: f64) {
+                self.set(field, FieldValue::F64(value));
+            }
+
+            fn record_bool(&mut self, field: &field::Field, value: bool) {
+                self.set(field, FieldValue::Bool(value));
+            }
+
+            fn record_str(&mut self, field: &field::Field, value: &str) {
+                let range = push_str(&mut self.p.string, value);
+                self.set(field, FieldValue::Str(range));
+            }
+
+            fn record_bytes(&mut self, field: &field::Field, value: &[u8]) {
+                let start = self.p.bytes.len();
+                self.p.bytes.extend_from_slice(value);
+                let range = start..self.p.bytes.len();
+                self.set(field, FieldValue::Bytes(range));
+            }
+
+            fn record_debug(&mut self, field: &field::Field, value: &dyn std::fmt::Debug) {
                 let str_start = self.p.string.len();
                 self.p
                     .string
@@ -145,12 +540,29 @@ impl LogEventProvider {
                         panic!("could not format field {:?} to string", field.name())
                     });
                 let str_end = self.p.string.len();
+                self.set(field, FieldValue::Debug(str_start..str_end));
+            }
 
-                let field_idx = self
-                    .fields
-                    .start
-                    .checked_add(field.index()).expect("the field info should already exist at this index, so the index should also not overflow usize");
-                self.p.field_infos[field_idx].value = str_start..str_end;
+            fn record_error(
+                &mut self,
+                field: &field::Field,
+                value: &(dyn std::error::Error + 'static),
+            ) {
+                let start = self.p.errors.len();
+                let mut error = Some(value);
+                while let Some(current) = error {
+                    let str_start = self.p.string.len();
+                    self.p
+                        .string
+                        .write_fmt(format_args!("{current}"))
+                        .unwrap_or_else(|_| {
+                            panic!("could not format error {:?} to string", field.name())
+                        });
+                    self.p.errors.push(str_start..self.p.string.len());
+                    error = current.source();
+                }
+                let range = start..self.p.errors.len();
+                self.set(field, FieldValue::Error(range));
             }
         }
         V {
@@ -160,6 +572,17 @@ impl LogEventProvider {
     }
 }
 
+fn offset(range: Range<usize>, by: usize) -> Range<usize> {
+    range.start + by..range.end + by
+}
+
+/// Appends `value` to `string`, returning where it was placed.
+fn push_str(string: &mut String, value: &str) -> Range<usize> {
+    let start = string.len();
+    string.push_str(value);
+    start..string.len()
+}
+
 impl Default for LogEventProvider {
     fn default() -> Self {
         Self::new()
@@ -185,15 +608,21 @@ impl EventProvider for LogEventProvider {
         };
         for field in iter {
             self.field_infos.push(FieldInfo {
-                value: usize::MAX..usize::MAX,
+                value: None,
                 name: field.name(),
             });
         }
         let field_end = self.field_infos.len();
 
+        let created = Instant::now();
+        self.epoch.get_or_insert(created);
+
         let mut event = LogEvent {
             fields: field_start..field_end,
             metadata: info.metadata,
+            created,
+            span_timing: info.is_span.then(|| SpanTiming::new(created)),
+            thread: self.thread_index(info.thread),
         };
         if let Some(values) = info.values_early {
             values.record(&mut self.make_visitor_impl(&mut event));
@@ -206,28 +635,147 @@ impl EventProvider for LogEventProvider {
         self.make_visitor_impl(event)
     }
 
-    #[inline]
-    fn should_span_enter() -> bool {
-        false
+    fn span_enter(&mut self, _: usize, event: &mut Self::Event) {
+        if let Some(timing) = &mut event.span_timing {
+            timing.enter(Instant::now());
+        }
     }
 
-    #[inline]
-    fn should_span_exit() -> bool {
-        false
+    fn span_exit(&mut self, _: usize, event: &mut Self::Event, unwinding: bool) {
+        if let Some(timing) = &mut event.span_timing {
+            timing.exit(Instant::now());
+            timing.unwound |= unwinding;
+        }
+    }
+
+    fn span_close(&mut self, _: usize, event: &mut Self::Event, unwinding: bool) {
+        if let Some(timing) = &mut event.span_timing {
+            timing.close(Instant::now());
+            timing.unwound |= unwinding;
+        }
+    }
+
+    fn span_follows_from(&mut self, id: usize, _: &mut Self::Event, follows: usize) {
+        self.follows_from.push((id, follows));
+    }
+
+    /// Returns the length of the [content string](LogEventProvider::string) and
+    /// [bytes](LogEventProvider::bytes), not counting parts which only belong to removed
+    /// events.
+    fn used_bytes(&self) -> usize {
+        self.string.len() + self.bytes.len() - self.dead_bytes
+    }
+
+    /// Reclaims the parts of the content string, bytes, errors and field infos which belonged
+    /// to the removed events, once they make up at least half of them.
+    fn nodes_removed<'a>(
+        &mut self,
+        removed: impl Iterator<Item = (usize, Self::Event)>,
+        remaining: impl Iterator<Item = (usize, &'a mut Self::Event)>,
+    ) {
+        let mut removed_ids = Vec::new();
+        for (id, event) in removed {
+            removed_ids.push(id);
+            self.dead_fields += event.fields.len();
+            self.dead_bytes += self.field_infos[event.fields]
+                .iter()
+                .filter_map(|info| info.value.as_ref())
+                .map(|value| value.stored_len(&self.errors))
+                .sum::<usize>();
+        }
+        // removed in order of creation, so the IDs are sorted
+        self.follows_from.retain(|(id, follows)| {
+            removed_ids.binary_search(id).is_err() && removed_ids.binary_search(follows).is_err()
+        });
+
+        if self.dead_bytes * 2 >= self.string.len() + self.bytes.len()
+            || self.dead_fields * 2 >= self.field_infos.len()
+        {
+            self.compact(remaining.map(|(_, event)| event));
+        }
+    }
+
+    fn fork(&self) -> Option<Fork<Self>> {
+        self.buffering.then(|| Fork::new(Self::new()))
+    }
+
+    /// Appends the content string, bytes, errors and field infos of `fork` to those of
+    /// `self`.
+    fn merge<'a>(&mut self, fork: &mut Self, events: impl Iterator<Item = &'a mut LogEvent>) {
+        if self.string.is_empty()
+            && self.bytes.is_empty()
+            && self.errors.is_empty()
+            && self.field_infos.is_empty()
+            && self.threads.is_empty()
+        {
+            // nothing to rebase the events onto, the fork's buffers can be taken as they are
+            mem::swap(&mut self.string, &mut fork.string);
+            mem::swap(&mut self.bytes, &mut fork.bytes);
+            mem::swap(&mut self.errors, &mut fork.errors);
+            mem::swap(&mut self.field_infos, &mut fork.field_infos);
+            mem::swap(&mut self.threads, &mut fork.threads);
+            self.epoch = fork.epoch.take();
+            return;
+        }
+        let string_offset = self.string.len();
+        let bytes_offset = self.bytes.len();
+        let errors_offset = self.errors.len();
+        let field_offset = self.field_infos.len();
+        self.string.push_str(&fork.string);
+        self.bytes.extend_from_slice(&fork.bytes);
+        self.errors
+            .extend(fork.errors.drain(..).map(|error| offset(error, string_offset)));
+        self.field_infos
+            .extend(fork.field_infos.drain(..).map(|info| FieldInfo {
+                value: info
+                    .value
+                    .map(|value| value.offset(string_offset, bytes_offset, errors_offset)),
+                name: info.name,
+            }));
+        let threads: Vec<usize> = fork
+            .threads
+            .drain(..)
+            .map(|info| {
+                match self.threads.iter().rposition(|known| known.id == info.id) {
+                    Some(index) => index,
+                    None => {
+                        self.threads.push(info);
+                        self.threads.len() - 1
+                    }
+                }
+            })
+            .collect();
+        for event in events {
+            event.fields = offset(Range::clone(&event.fields), field_offset);
+            event.thread = threads[event.thread];
+        }
+        if let Some(epoch) = fork.epoch.take() {
+            self.epoch = Some(self.epoch.map_or(epoch, |known| known.min(epoch)));
+        }
+        fork.string.clear();
+        fork.bytes.clear();
     }
 }
 
 #[cfg(feature = "serde")]
 mod serde {
-    use std::ops::Range;
+    use std::{
+        collections::BTreeMap,
+        ops::Range,
+        time::{Duration, Instant},
+    };
 
+    use forrust_fire_tree::ashes::serde::SerOptions;
     use serde::{Serialize, ser::SerializeMap};
     use tracing_serde::{AsSerde, SerializeLevel};
 
-    use crate::providers::log::{LogAshes, LogEvent};
+    use crate::{
+        providers::log::{FieldValue, LogAshes, LogEvent, LogEventProvider},
+        stream::StreamEventProvider,
+    };
 
     struct SerializeEventCtx<'a> {
-        ashes: &'a LogAshes,
+        provider: &'a LogEventProvider,
         event: &'a LogEvent,
     }
 
@@ -238,15 +786,106 @@ mod serde {
         {
             let mut map = serializer.serialize_map(Some(self.event.fields.len()))?;
             for field_idx in Range::clone(&self.event.fields) {
-                let info = &self.ashes.provider.field_infos[field_idx];
-                map.serialize_entry(info.name, &info.get_value(&self.ashes.provider.string))?;
+                let info = &self.provider.field_infos[field_idx];
+                let value = SerializeValue {
+                    provider: self.provider,
+                    value: info.value.as_ref(),
+                };
+                map.serialize_entry(info.name, &value)?;
             }
             map.end()
         }
     }
 
+    struct SerializeValue<'a> {
+        provider: &'a LogEventProvider,
+        value: Option<&'a FieldValue>,
+    }
+
+    impl<'a> Serialize for SerializeValue<'a> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            let Some(value) = self.value else {
+                return serializer.serialize_none();
+            };
+            match value {
+                &FieldValue::I64(v) => serializer.serialize_i64(v),
+                &FieldValue::U64(v) => serializer.serialize_u64(v),
+                // many JSON implementations can't go past 64 bits
+                &FieldValue::I128(v) => match i64::try_from(v) {
+                    Ok(v) => serializer.serialize_i64(v),
+                    Err(_) => serializer.collect_str(&v),
+                },
+                &FieldValue::U128(v) => match u64::try_from(v) {
+                    Ok(v) => serializer.serialize_u64(v),
+                    Err(_) => serializer.collect_str(&v),
+                },
+                &FieldValue::F64(v) => serializer.serialize_f64(v),
+                &FieldValue::Bool(v) => serializer.serialize_bool(v),
+                FieldValue::Bytes(_) => {
+                    serializer.serialize_bytes(value.as_bytes(&self.provider.bytes).unwrap())
+                }
+                FieldValue::Str(_) | FieldValue::Debug(_) => {
+                    serializer.serialize_str(value.as_str(&self.provider.string).unwrap())
+                }
+                FieldValue::Error(_) => serializer.collect_seq(
+                    value
+                        .as_error_chain(&self.provider.errors, &self.provider.string)
+                        .unwrap(),
+                ),
+            }
+        }
+    }
+
+    struct SerializeTime<'a> {
+        epoch: Option<Instant>,
+        event: &'a LogEvent,
+    }
+
+    impl<'a> SerializeTime<'a> {
+        fn since_epoch(&self, instant: Instant) -> u64 {
+            let epoch = self.epoch.unwrap_or(instant);
+            nanos(instant.saturating_duration_since(epoch))
+        }
+    }
+
+    fn nanos(duration: Duration) -> u64 {
+        u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+    }
+
+    impl<'a> Serialize for SerializeTime<'a> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            let timing = self.event.span_timing.as_ref();
+            let mut map = serializer.serialize_map(Some(if timing.is_some() { 7 } else { 1 }))?;
+            map.serialize_entry("created", &self.since_epoch(self.event.created))?;
+            if let Some(timing) = timing {
+                let since_epoch = |instant: Option<Instant>| instant.map(|i| self.since_epoch(i));
+                map.serialize_entry("entered", &since_epoch(timing.first_entered))?;
+                map.serialize_entry("exited", &since_epoch(timing.last_exited))?;
+                map.serialize_entry("closed", &since_epoch(timing.closed))?;
+                map.serialize_entry("busy", &nanos(timing.busy))?;
+                map.serialize_entry("idle", &nanos(timing.idle))?;
+                map.serialize_entry("entries", &timing.entries)?;
+            }
+            map.end()
+        }
+    }
+
+    #[derive(Serialize)]
+    struct SerializeThread<'a> {
+        id: usize,
+        name: Option<&'a str>,
+    }
+
     #[derive(Serialize)]
     struct SerializeEvent<'a> {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<usize>,
         name: &'static str,
         target: &'a str,
         level: SerializeLevel<'a>,
@@ -255,29 +894,127 @@ mod serde {
         file: Option<&'a str>,
         line: Option<u32>,
         is_span: bool,
+        #[serde(skip_serializing_if = "is_false")]
+        unwound: bool,
+        thread: SerializeThread<'a>,
+        time: SerializeTime<'a>,
         ctx: SerializeEventCtx<'a>,
     }
 
+    impl<'a> SerializeEvent<'a> {
+        fn new(provider: &'a LogEventProvider, id: Option<usize>, event: &'a LogEvent) -> Self {
+            let metadata = event.metadata;
+            SerializeEvent {
+                id,
+                time: SerializeTime {
+                    epoch: provider.epoch,
+                    event,
+                },
+                thread: SerializeThread {
+                    id: event.thread,
+                    name: provider.threads[event.thread].name.as_deref(),
+                },
+                ctx: SerializeEventCtx { provider, event },
+                name: metadata.name(),
+                target: metadata.target(),
+                level: metadata.level().as_serde(),
+                module_path: metadata.module_path(),
+                file: metadata.file(),
+                line: metadata.line(),
+                is_span: metadata.is_span(),
+                unwound: event.span_timing.as_ref().is_some_and(|timing| timing.unwound),
+            }
+        }
+    }
+
     impl Serialize for LogAshes {
         fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
         where
             S: serde::Serializer,
         {
-            self.ash
-                .serializable_with(|event| {
-                    let metadata = event.metadata;
-                    SerializeEvent {
-                        ctx: SerializeEventCtx { ashes: self, event },
-                        name: metadata.name(),
-                        target: metadata.target(),
-                        level: metadata.level().as_serde(),
-                        module_path: metadata.module_path(),
-                        file: metadata.file(),
-                        line: metadata.line(),
-                        is_span: metadata.is_span(),
-                    }
-                })
+            self.serializable_opts(SerOptions::new())
                 .serialize(serializer)
         }
     }
+
+    impl LogAshes {
+        /// Returns a serializable object which only serializes the parts of the tree
+        /// selected by `opts`.
+        ///
+        /// This is useful for trees which are too large to view at once. See [`SerOptions`]
+        /// for details.
+        ///
+        /// # Panics
+        ///
+        /// Serializing panics if [`SerOptions::start`] does not refer to an existing branch.
+        pub fn serializable_opts(&self, opts: SerOptions) -> impl Serialize + '_ {
+            SerializeAshes {
+                tree: self.ash.serializable_with_ctx_opts(opts, |ctx| {
+                    let id = ctx.branch.fire_id().map(|id| id.value());
+                    SerializeNode {
+                        v: SerializeEvent::new(&self.provider, id, ctx.payload),
+                        dropped: id.map_or(0, |id| self.dropped.under(id)),
+                    }
+                }),
+                follows_from: SerializeFollowsFrom(&self.provider.follows_from),
+                dropped: self.dropped.at_root(),
+            }
+        }
+    }
+
+    #[derive(Serialize)]
+    struct SerializeNode<'a> {
+        v: SerializeEvent<'a>,
+        #[serde(skip_serializing_if = "is_zero")]
+        dropped: usize,
+    }
+
+    fn is_zero(n: &usize) -> bool {
+        *n == 0
+    }
+
+    fn is_false(b: &bool) -> bool {
+        !*b
+    }
+
+    #[derive(Serialize)]
+    struct SerializeAshes<'a, Tree> {
+        #[serde(flatten)]
+        tree: Tree,
+        #[serde(skip_serializing_if = "SerializeFollowsFrom::is_empty")]
+        follows_from: SerializeFollowsFrom<'a>,
+        #[serde(skip_serializing_if = "is_zero")]
+        dropped: usize,
+    }
+
+    struct SerializeFollowsFrom<'a>(&'a [(usize, usize)]);
+
+    impl<'a> SerializeFollowsFrom<'a> {
+        fn is_empty(&self) -> bool {
+            self.0.is_empty()
+        }
+    }
+
+    impl<'a> Serialize for SerializeFollowsFrom<'a> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            let mut edges = BTreeMap::<usize, Vec<usize>>::new();
+            for &(id, follows) in self.0 {
+                edges.entry(id).or_default().push(follows);
+            }
+            edges.serialize(serializer)
+        }
+    }
+
+    impl StreamEventProvider for LogEventProvider {
+        fn serializable_event<'a>(
+            &'a self,
+            id: usize,
+            event: &'a Self::Event,
+        ) -> impl Serialize + 'a {
+            SerializeEvent::new(self, Some(id), event)
+        }
+    }
 }
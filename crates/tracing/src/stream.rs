@@ -0,0 +1,229 @@
+//! Streaming capture of nodes as JSON lines.
+//!
+//! A [`ForestFireSubscriber`] keeps its whole tree in memory until it is [burned]; if the
+//! process aborts before that, the tree is gone. With [`ForestFireSubscriber::with_stream`],
+//! every node is additionally written to an [`io::Write`] as soon as it is created, one JSON
+//! object per line:
+//!
+//! ```json
+//! {"id":0,"parent":null,"v":{"name":"hello!", ...}}
+//! {"id":1,"parent":0,"v":{"name":"event src/main.rs:4", ...}}
+//! ```
+//!
+//! `parent` is `null` for children of root. Nodes are always written after their parent.
+//!
+//! Such a file can be turned back into a tree with [`load_stream`], which tolerates the
+//! last line being cut off mid-write.
+//!
+//! Only the data available when a node is created is written: values recorded into a span
+//! later on (through [`tracing::Span::record`]) will not show up in the stream.
+//!
+//! [`ForestFireSubscriber`]: crate::ForestFireSubscriber
+//! [`ForestFireSubscriber::with_stream`]: crate::ForestFireSubscriber::with_stream
+//! [burned]: crate::ForestFireSubscriber::burn
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug},
+    io,
+};
+
+use forrust_fire_tree::{
+    ashes::Ashes,
+    fire::{self, serde::FireDeserStorage},
+};
+use serde::{
+    Serialize,
+    de::{DeserializeOwned, DeserializeSeed},
+};
+
+use crate::EventProvider;
+
+/// An [`EventProvider`] whose events can be written to a node stream.
+///
+/// See the [module documentation](self).
+pub trait StreamEventProvider: EventProvider {
+    /// Returns a serializable view of the given event.
+    ///
+    /// This is written as the `v` key of the event's line.
+    fn serializable_event<'a>(&'a self, id: usize, event: &'a Self::Event) -> impl Serialize + 'a;
+}
+
+#[derive(Serialize)]
+struct LineSer<V> {
+    id: usize,
+    parent: Option<usize>,
+    v: V,
+}
+
+type WriteLine<P> = fn(
+    &mut Vec<u8>,
+    fire::BranchId,
+    fire::BranchId,
+    &P,
+    &<P as EventProvider>::Event,
+) -> serde_json::Result<()>;
+
+fn write_line<P: StreamEventProvider>(
+    buffer: &mut Vec<u8>,
+    id: fire::BranchId,
+    parent: fire::BranchId,
+    provider: &P,
+    event: &P::Event,
+) -> serde_json::Result<()> {
+    serde_json::to_writer(
+        &mut *buffer,
+        &LineSer {
+            id: id.value(),
+            parent: if parent.is_root() {
+                None
+            } else {
+                Some(parent.value())
+            },
+            v: provider.serializable_event(id.value(), event),
+        },
+    )?;
+    buffer.push(b'\n');
+    Ok(())
+}
+
+pub(crate) struct NodeStream<P: EventProvider> {
+    writer: Box<dyn io::Write + Send>,
+    // reused between lines so that every node is written with a single `write_all`
+    buffer: Vec<u8>,
+    error: Option<io::Error>,
+    // captured when the stream is created since that's the only place where
+    // we know that `P: StreamEventProvider`
+    write_line: WriteLine<P>,
+}
+
+impl<P: EventProvider> NodeStream<P> {
+    pub(crate) fn new(writer: Box<dyn io::Write + Send>) -> Self
+    where
+        P: StreamEventProvider,
+    {
+        Self {
+            writer,
+            buffer: Vec::new(),
+            error: None,
+            write_line: write_line::<P>,
+        }
+    }
+
+    pub(crate) fn write(
+        &mut self,
+        id: fire::BranchId,
+        parent: fire::BranchId,
+        provider: &P,
+        event: &P::Event,
+    ) {
+        if self.error.is_some() {
+            return;
+        }
+
+        self.buffer.clear();
+        let result = (self.write_line)(&mut self.buffer, id, parent, provider, event)
+            .map_err(io::Error::from)
+            .and_then(|()| self.writer.write_all(&self.buffer))
+            // flushing every line is the whole point: anything left in a buffer
+            // is lost if the process gets killed
+            .and_then(|()| self.writer.flush());
+        if let Err(err) = result {
+            self.error = Some(err);
+        }
+    }
+
+    pub(crate) fn finish(self) -> io::Result<Box<dyn io::Write + Send>> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.writer),
+        }
+    }
+}
+
+impl<P: EventProvider> Debug for NodeStream<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NodeStream")
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Rebuilds a tree from a node stream written by [`ForestFireSubscriber::with_stream`].
+///
+/// Each node's payload is deserialized from its `v` key as a `T`; if you don't have a
+/// dedicated type for it, [`serde_json::Value`] works for any stream.
+///
+/// If the last line of the stream is incomplete (it does not end in a newline and cannot
+/// be parsed), it is silently dropped: this is what a stream looks like if the writing
+/// process was killed in the middle of a write. A malformed line anywhere else results in
+/// an error.
+///
+/// To load the nodes into a [`ForestFire`] instead (for example, to keep adding to it),
+/// see [`load_stream_into`].
+///
+/// # Errors
+///
+/// Returns an error if reading fails, if a line (other than a truncated last one) is not
+/// a valid node, if a node ID appears twice, or if a node refers to a parent which did not
+/// appear before it.
+///
+/// [`ForestFireSubscriber::with_stream`]: crate::ForestFireSubscriber::with_stream
+/// [`ForestFire`]: forrust_fire_tree::fire::ForestFire
+pub fn load_stream<T: DeserializeOwned>(reader: impl io::BufRead) -> io::Result<Ashes<T>> {
+    let mut storage = FireDeserStorage::new();
+    load_stream_into(reader, &mut storage, fire::BranchId::ROOT)?;
+    Ok(storage.fire.burn())
+}
+
+/// Loads the nodes of a node stream written by [`ForestFireSubscriber::with_stream`] into
+/// an existing [`ForestFire`].
+///
+/// Nodes which were children of root in the stream are added as children of `parent`.
+/// Returns a mapping from each node's ID in the stream to its branch ID in
+/// `storage.fire`.
+///
+/// See [`load_stream`] for how the stream is read.
+///
+/// # Errors
+///
+/// See [`load_stream`](load_stream#errors). If an error is returned, nodes read before the
+/// error are kept in `storage.fire`.
+///
+/// # Panics
+///
+/// Panics if `parent` is not an [existing](forrust_fire_tree::fire::ForestFire::exists) branch of `storage.fire`.
+///
+/// [`ForestFireSubscriber::with_stream`]: crate::ForestFireSubscriber::with_stream
+/// [`ForestFire`]: forrust_fire_tree::fire::ForestFire
+pub fn load_stream_into<T: DeserializeOwned>(
+    mut reader: impl io::BufRead,
+    storage: &mut FireDeserStorage<T>,
+    parent: fire::BranchId,
+) -> io::Result<HashMap<usize, fire::BranchId>> {
+    let mut ids = HashMap::new();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        let complete = line.last() == Some(&b'\n');
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+
+        let mut deserializer = serde_json::Deserializer::from_slice(&line);
+        let result = storage
+            .node_seed(parent, &mut ids)
+            .deserialize(&mut deserializer)
+            .and_then(|_| deserializer.end());
+        match result {
+            Ok(()) => {}
+            // a line without a newline can only be the last one
+            Err(_) if !complete => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(ids)
+}
@@ -4,6 +4,24 @@ compile_error!("`forrust_fire_tracing` requires the `serde` feature for testing"
 #[rustfmt::skip]
 mod tracing1;
 
+/// Removes the timing and thread information of every node, since they differ between runs.
+#[cfg(feature = "serde")]
+fn strip_volatile(value: &mut serde_json::Value) -> &mut serde_json::Value {
+    if let Some(map) = value.as_object_mut() {
+        for (key, child) in map.iter_mut() {
+            if key == "v" {
+                if let Some(v) = child.as_object_mut() {
+                    v.remove("time");
+                    v.remove("thread");
+                }
+            } else {
+                strip_volatile(child);
+            }
+        }
+    }
+    value
+}
+
 #[test]
 #[cfg(feature = "serde")]
 fn tracing1() {
@@ -13,14 +31,16 @@ fn tracing1() {
 
     let ash_trayce = LogEventProvider::new().run(tracing1::run);
 
-    let value = serde_json::to_value(ash_trayce).unwrap();
+    let mut value = serde_json::to_value(ash_trayce).unwrap();
     println!("{value:#}");
+    strip_volatile(&mut value);
 
     assert_eq!(
         value,
         json!({
           "0": {
             "v": {
+              "id": 0,
               "ctx": {
                 "message": "woa"
               },
@@ -35,8 +55,9 @@ fn tracing1() {
           },
           "1": {
             "v": {
+              "id": 1,
               "ctx": {
-                "two_plus_two": "4"
+                "two_plus_two": 4
               },
               "file": "crates/tracing/src/test/tracing1.rs",
               "is_span": true,
@@ -48,6 +69,7 @@ fn tracing1() {
             },
             "0": {
               "v": {
+                "id": 2,
                 "ctx": {
                   "message": "yaaa"
                 },
@@ -62,6 +84,7 @@ fn tracing1() {
             },
             "1": {
               "v": {
+                "id": 3,
                 "ctx": {},
                 "file": "crates/tracing/src/test/tracing1.rs",
                 "is_span": true,
@@ -73,6 +96,7 @@ fn tracing1() {
               },
               "0": {
                 "v": {
+                  "id": 4,
                   "ctx": {
                     "message": "hello world!"
                   },
@@ -87,6 +111,7 @@ fn tracing1() {
               },
               "1": {
                 "v": {
+                  "id": 5,
                   "ctx": {
                     "message": "hello world, but in an evil manner"
                   },
@@ -104,3 +129,1105 @@ fn tracing1() {
         })
     );
 }
+
+#[test]
+#[cfg(feature = "serde")]
+fn stream() {
+    use std::{
+        io::{self, Write},
+        sync::{Arc, Mutex},
+    };
+
+    use forrust_fire_tree::fire::ForestFire;
+
+    use crate::{
+        ForestFireSubscriber, nothread_run_subscriber_ret, providers::log::LogEventProvider,
+        stream::load_stream,
+    };
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let buf = SharedBuf::default();
+    let subscriber = ForestFireSubscriber::new(ForestFire::new(), LogEventProvider::new())
+        .with_stream(buf.clone());
+    let ((), ash_trayce) = nothread_run_subscriber_ret(subscriber, tracing1::run);
+    // spans are streamed before being entered, so their timing will differ
+    let mut expected = serde_json::to_value(ash_trayce).unwrap();
+    strip_volatile(&mut expected);
+
+    let stream = buf.0.lock().unwrap().clone();
+    println!("{}", String::from_utf8_lossy(&stream));
+    assert_eq!(stream.iter().filter(|&&b| b == b'\n').count(), 6);
+
+    let loaded = load_stream::<serde_json::Value>(&stream[..]).unwrap();
+    assert_eq!(
+        strip_volatile(&mut serde_json::to_value(loaded).unwrap()),
+        &expected
+    );
+
+    // cut the last line in half, as if the process died while writing it
+    let truncated = &stream[..stream.len() - 20];
+    let loaded = load_stream::<serde_json::Value>(truncated).unwrap();
+    expected["1"]["1"].as_object_mut().unwrap().remove("1");
+    assert_eq!(
+        strip_volatile(&mut serde_json::to_value(loaded).unwrap()),
+        &expected
+    );
+
+    // a broken line in the middle is still an error
+    let mut broken = stream.clone();
+    broken.splice(0..0, b"{\"id\":\n".iter().copied());
+    assert!(load_stream::<serde_json::Value>(&broken[..]).is_err());
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn typed_fields() {
+    use serde_json::json;
+
+    use crate::providers::{
+        ProviderExt,
+        log::{FieldValue, LogEventProvider},
+    };
+
+    let log_ashes = LogEventProvider::new().run(|| {
+        let span = tracing::info_span!(
+            "typed",
+            later = tracing::field::Empty,
+            never = tracing::field::Empty,
+        );
+        span.in_scope(|| {
+            tracing::info!(
+                int = -3,
+                uint = 3u64,
+                big = i128::MIN,
+                ubig = u128::MAX,
+                float = 0.5,
+                flag = true,
+                text = "\"quoted\"",
+                bytes = &b"\xff\x00"[..],
+                debug = ?Some(1),
+                "typed"
+            );
+        });
+        span.record("later", 7);
+    });
+
+    let value = serde_json::to_value(&log_ashes).unwrap();
+    println!("{value:#}");
+    assert_eq!(value["0"]["v"]["ctx"], json!({ "later": 7, "never": null }));
+    let ctx = &value["0"]["0"]["v"]["ctx"];
+    assert_eq!(ctx["int"], -3);
+    assert_eq!(ctx["uint"], 3);
+    // too big for most JSON parsers
+    assert_eq!(ctx["big"], i128::MIN.to_string());
+    assert_eq!(ctx["ubig"], u128::MAX.to_string());
+    assert_eq!(ctx["float"], 0.5);
+    assert_eq!(ctx["flag"], true);
+    // strings are not `Debug` formatted, unlike other values
+    assert_eq!(ctx["text"], "\"quoted\"");
+    assert_eq!(ctx["bytes"], json!([255, 0]));
+    assert_eq!(ctx["debug"], "Some(1)");
+    assert_eq!(ctx["message"], "typed");
+
+    let provider = &log_ashes.provider;
+    let info = provider.field_infos.iter().find(|info| info.name == "bytes").unwrap();
+    let bytes = info.value.as_ref().and_then(|v| v.as_bytes(&provider.bytes));
+    assert_eq!(bytes, Some(&b"\xff\x00"[..]));
+    let info = provider.field_infos.iter().find(|info| info.name == "text").unwrap();
+    assert!(matches!(info.value, Some(FieldValue::Str(_))));
+    assert_eq!(info.get_value(&provider.string), Some("\"quoted\""));
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn error_chains() {
+    use std::{error::Error, fmt, io};
+
+    use serde_json::json;
+
+    use crate::providers::{ProviderExt, log::LogEventProvider};
+
+    #[derive(Debug)]
+    struct ConfigError(io::Error);
+
+    impl fmt::Display for ConfigError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("could not load config")
+        }
+    }
+
+    impl Error for ConfigError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    let log_ashes = LogEventProvider::new().run(|| {
+        let error = ConfigError(io::Error::new(io::ErrorKind::NotFound, "no such file"));
+        tracing::error!(error = &error as &dyn Error, "startup failed");
+        let lone = io::Error::other("lonely");
+        tracing::warn!(error = &lone as &dyn Error);
+    });
+
+    let value = serde_json::to_value(&log_ashes).unwrap();
+    println!("{value:#}");
+    assert_eq!(
+        value["0"]["v"]["ctx"]["error"],
+        json!(["could not load config", "no such file"])
+    );
+    assert_eq!(value["1"]["v"]["ctx"]["error"], json!(["lonely"]));
+
+    let provider = &log_ashes.provider;
+    let info = provider.field_infos.iter().find(|info| info.name == "error").unwrap();
+    let chain: Vec<_> = info
+        .value
+        .as_ref()
+        .and_then(|v| v.as_error_chain(&provider.errors, &provider.string))
+        .unwrap()
+        .collect();
+    assert_eq!(chain, ["could not load config", "no such file"]);
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn span_timing() {
+    use std::{thread, time::Duration};
+
+    use tracing::{info, info_span};
+
+    use crate::providers::{ProviderExt, log::LogEventProvider};
+
+    const NAP: Duration = Duration::from_millis(20);
+    let nap_ns = NAP.as_nanos() as u64;
+
+    let ash_trayce = LogEventProvider::new().run(|| {
+        info!("before");
+        let span = info_span!("napping");
+        thread::sleep(NAP); // idle
+        span.in_scope(|| thread::sleep(NAP)); // busy
+        let clone = span.clone();
+        drop(span);
+        thread::sleep(NAP); // idle, still open through `clone`
+        clone.in_scope(|| thread::sleep(NAP)); // busy
+        drop(clone);
+        info_span!("never entered");
+    });
+
+    let value = serde_json::to_value(ash_trayce).unwrap();
+    println!("{value:#}");
+
+    assert_eq!(value["0"]["v"]["time"], serde_json::json!({ "created": 0 }));
+
+    let time = &value["1"]["v"]["time"];
+    let get = |key: &str| {
+        time[key]
+            .as_u64()
+            .unwrap_or_else(|| panic!("{key} is missing"))
+    };
+    assert!(get("busy") >= 2 * nap_ns);
+    assert!(get("idle") >= 2 * nap_ns);
+    assert!(get("created") <= get("entered"));
+    assert!(get("entered") + get("busy") <= get("exited"));
+    assert!(get("exited") <= get("closed"));
+    assert_eq!(get("closed") - get("created"), get("busy") + get("idle"));
+    assert_eq!(get("entries"), 2);
+
+    let time = &value["2"]["v"]["time"];
+    assert!(time["entered"].is_null());
+    assert!(time["exited"].is_null());
+    assert!(!time["closed"].is_null());
+    assert_eq!(time["busy"], 0);
+    assert_eq!(time["entries"], 0);
+}
+
+#[test]
+fn span_hooks() {
+    use tracing::info_span;
+
+    use crate::{EventInfo, EventProvider, nothread_run_forest_ret};
+
+    #[derive(Debug, Default, PartialEq, Eq)]
+    struct Counts {
+        enters: usize,
+        exits: usize,
+        closes: usize,
+    }
+
+    /// Counts hook calls per span, and logs them in the order they were made.
+    #[derive(Default)]
+    struct CountingProvider {
+        log: Vec<(&'static str, usize)>,
+    }
+
+    impl EventProvider for CountingProvider {
+        type Event = Counts;
+
+        fn make_event(&mut self, _: usize, _: EventInfo) -> Self::Event {
+            Counts::default()
+        }
+
+        fn span_enter(&mut self, id: usize, event: &mut Self::Event) {
+            event.enters += 1;
+            self.log.push(("enter", id));
+        }
+
+        fn span_exit(&mut self, id: usize, event: &mut Self::Event, _: bool) {
+            event.exits += 1;
+            self.log.push(("exit", id));
+        }
+
+        fn span_close(&mut self, id: usize, event: &mut Self::Event, _: bool) {
+            event.closes += 1;
+            self.log.push(("close", id));
+        }
+
+        fn should_use_visitor() -> bool {
+            false
+        }
+    }
+
+    let ((), ash_trayce) = nothread_run_forest_ret(CountingProvider::default(), || {
+        let outer = info_span!("outer");
+        let inner = outer.in_scope(|| info_span!("inner"));
+        for _ in 0..3 {
+            let _outer = outer.enter();
+            // entering a span which is already entered
+            outer.in_scope(|| inner.in_scope(|| {}));
+        }
+        drop(outer);
+        inner.in_scope(|| {});
+    });
+
+    let ash = &ash_trayce.ash;
+    let outer = ash.branch(ash.root().child(0));
+    let inner = ash.branch(outer.child(0));
+    assert_eq!(
+        outer.payload().unwrap(),
+        &Counts {
+            enters: 7,
+            exits: 7,
+            closes: 1
+        }
+    );
+    assert_eq!(
+        inner.payload().unwrap(),
+        &Counts {
+            enters: 4,
+            exits: 4,
+            closes: 1
+        }
+    );
+
+    let mut expected = vec![("enter", 0), ("exit", 0)];
+    for _ in 0..3 {
+        expected.extend([
+            ("enter", 0),
+            ("enter", 0),
+            ("enter", 1),
+            ("exit", 1),
+            ("exit", 0),
+            ("exit", 0),
+        ]);
+    }
+    expected.extend([("close", 0), ("enter", 1), ("exit", 1), ("close", 1)]);
+    assert_eq!(ash_trayce.provider.log, expected);
+}
+
+#[test]
+fn current_span() {
+    use tracing::{Span, info_span};
+
+    use crate::{nothread_run_forest_ret, providers::log::LogEventProvider};
+
+    let ((), _) = nothread_run_forest_ret(LogEventProvider::new(), || {
+        assert!(Span::current().is_none());
+        let outer = info_span!("outer");
+        outer.in_scope(|| {
+            assert_eq!(Span::current().metadata().unwrap().name(), "outer");
+            assert_eq!(Span::current().id(), outer.id());
+            let inner = info_span!("inner");
+            inner.in_scope(|| assert_eq!(Span::current().id(), inner.id()));
+            assert_eq!(Span::current().id(), outer.id());
+        });
+        assert!(Span::current().is_none());
+    });
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn follows_from() {
+    use serde_json::json;
+    use tracing::info_span;
+
+    use crate::providers::{ProviderExt, log::LogEventProvider};
+
+    let ash_trayce = LogEventProvider::new().run(|| {
+        let spawner = info_span!("spawner");
+        let other = info_span!("other");
+        let _ = spawner.in_scope(|| info_span!("child"));
+        let task = info_span!("task");
+        task.follows_from(&spawner);
+        task.follows_from(&other);
+        other.follows_from(&spawner);
+    });
+    assert_eq!(ash_trayce.provider.follows_from, [(3, 0), (3, 1), (1, 0)]);
+
+    let value = serde_json::to_value(&ash_trayce).unwrap();
+    println!("{value:#}");
+    assert_eq!(value["follows_from"], json!({ "1": [0], "3": [0, 1] }));
+    // the IDs refer to the nodes' `id`s rather than their position in the tree
+    assert_eq!(value["0"]["0"]["v"]["id"], 2);
+    assert_eq!(value["2"]["v"]["id"], 3);
+
+    // the tree can still be loaded
+    let loaded: forrust_fire_tree::ashes::Ashes<serde_json::Value> =
+        serde_json::from_value(value).unwrap();
+    assert_eq!(loaded.root().n_children(), 3);
+
+    // without any edges, the key is left out
+    let ash_trayce = LogEventProvider::new().run(tracing1::run);
+    let value = serde_json::to_value(&ash_trayce).unwrap();
+    assert!(value.get("follows_from").is_none());
+}
+
+#[test]
+fn filter_parse() {
+    use tracing::level_filters::LevelFilter;
+
+    use crate::filter::Filter;
+
+    let filter: Filter = "info, a=trace,a::quiet=off,,[loud]=debug".parse().unwrap();
+    assert_eq!(filter.max_level(), LevelFilter::TRACE);
+    assert!(filter.has_span_directives());
+    assert_eq!(
+        Filter::parse("warn").unwrap().max_level(),
+        LevelFilter::WARN
+    );
+    assert_eq!(Filter::parse("").unwrap().max_level(), LevelFilter::OFF);
+
+    for invalid in ["a=loud", "[x", "a[x{y=1}]=info", "[]=info", "=info"] {
+        assert!(
+            Filter::parse(invalid).is_err(),
+            "{invalid:?} should be invalid"
+        );
+    }
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn filter() {
+    use forrust_fire_tree::fire::ForestFire;
+    use tracing::{debug, debug_span, error, info, info_span, trace};
+
+    use crate::{
+        ForestFireSubscriber, filter::Filter, nothread_run_subscriber_ret,
+        providers::log::LogEventProvider,
+    };
+
+    /// Lists the messages (or names, for spans) of every node in pre-order.
+    fn messages(value: &serde_json::Value, out: &mut Vec<String>) {
+        for i in 0.. {
+            let Some(child) = value.get(i.to_string()) else {
+                break;
+            };
+            let v = &child["v"];
+            let message = v["ctx"]["message"].as_str().or(v["name"].as_str());
+            out.push(message.unwrap().to_owned());
+            messages(child, out);
+        }
+    }
+
+    let filter = Filter::parse("info,a=trace,a::quiet=off,b[loud]=debug").unwrap();
+    let subscriber =
+        ForestFireSubscriber::new(ForestFire::new(), LogEventProvider::new()).with_filter(filter);
+    let ((), ash_trayce) = nothread_run_subscriber_ret(subscriber, || {
+        info!(target: "b", "info in b");
+        debug!(target: "b", "dropped");
+        trace!(target: "a::x", "trace in a::x");
+        error!(target: "a::quiet", "dropped");
+        info_span!(target: "b", "loud").in_scope(|| {
+            debug!(target: "c", "debug in loud");
+            trace!(target: "c", "dropped");
+            info_span!(target: "c", "nested").in_scope(|| debug!("debug in nested"));
+        });
+        debug!(target: "b", "dropped");
+        // the span is enabled through its own directive, but not from another target
+        debug_span!(target: "b", "loud").in_scope(|| debug!("debug in debug loud"));
+        debug_span!(target: "c", "loud").in_scope(|| debug!("dropped"));
+    });
+
+    let mut out = Vec::new();
+    messages(&serde_json::to_value(&ash_trayce).unwrap(), &mut out);
+    assert_eq!(
+        out,
+        [
+            "info in b",
+            "trace in a::x",
+            "loud",
+            "debug in loud",
+            "nested",
+            "debug in nested",
+            "loud",
+            "debug in debug loud",
+        ]
+    );
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn explicit_parents() {
+    use std::thread;
+
+    use serde_json::json;
+    use tracing::{Span, dispatcher, info, info_span};
+
+    use crate::providers::{ProviderExt, log::LogEventProvider};
+
+    let ash_trayce = LogEventProvider::new().run(|| {
+        let a = info_span!("a");
+        let b = info_span!("b");
+        let _a = a.enter();
+        // contextual
+        info_span!("in a").in_scope(|| info!("in in a"));
+        info!("event in a");
+        // explicit parent
+        let _ = info_span!(parent: &b, "span in b");
+        info!(parent: &b, "event in b");
+        // explicit root
+        let _ = info_span!(parent: None, "root span");
+        info!(parent: None, "root event");
+        // `Span::none()` as a parent also means root
+        info!(parent: Span::none().id(), "another root event");
+
+        let dispatch = dispatcher::get_default(|dispatch| dispatch.clone());
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                dispatcher::with_default(&dispatch, || {
+                    // the other thread doesn't know about `a` being entered
+                    info!("root event from thread");
+                    info!(parent: &a, "event in a from thread");
+                    info_span!(parent: &b, "span in b from thread")
+                        .in_scope(|| info!("event in thread span"));
+                });
+            });
+        });
+    });
+
+    let value = serde_json::to_value(&ash_trayce).unwrap();
+    println!("{value:#}");
+
+    /// Strips everything but the names/messages of each node.
+    fn names(value: &serde_json::Value) -> serde_json::Value {
+        let mut out = serde_json::Map::new();
+        for (key, child) in value.as_object().unwrap() {
+            if key == "v" {
+                let message = &child["ctx"]["message"];
+                let name = if message.is_null() {
+                    &child["name"]
+                } else {
+                    message
+                };
+                out.insert("v".to_owned(), name.clone());
+            } else {
+                out.insert(key.clone(), names(child));
+            }
+        }
+        out.into()
+    }
+
+    assert_eq!(
+        names(&value),
+        json!({
+            "0": {
+                "v": "a",
+                "0": { "v": "in a", "0": { "v": "in in a" } },
+                "1": { "v": "event in a" },
+                "2": { "v": "event in a from thread" },
+            },
+            "1": {
+                "v": "b",
+                "0": { "v": "span in b" },
+                "1": { "v": "event in b" },
+                "2": { "v": "span in b from thread", "0": { "v": "event in thread span" } },
+            },
+            "2": { "v": "root span" },
+            "3": { "v": "root event" },
+            "4": { "v": "another root event" },
+            "5": { "v": "root event from thread" },
+        })
+    );
+}
+
+#[test]
+#[cfg(all(feature = "serde", feature = "tracing-subscriber"))]
+fn layer() {
+    use tracing_subscriber::{Layer, Registry, filter::filter_fn, layer::SubscriberExt};
+
+    use crate::{
+        layer::ForestFireLayer,
+        providers::{ProviderExt, log::LogEventProvider},
+    };
+
+    let mut expected = serde_json::to_value(LogEventProvider::new().run(tracing1::run)).unwrap();
+    strip_volatile(&mut expected);
+
+    // next to a fmt layer, the same tree is recorded
+    let layer = ForestFireLayer::new(LogEventProvider::new());
+    let handle = layer.handle();
+    let subscriber = Registry::default()
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::sink))
+        .with(layer);
+    tracing::subscriber::with_default(subscriber, tracing1::run);
+    assert_eq!(handle.node_count(), 6);
+    let mut value = serde_json::to_value(handle.burn()).unwrap();
+    assert_eq!(strip_volatile(&mut value), &expected);
+
+    // children of a span filtered out for the layer move up to its parent
+    let layer = ForestFireLayer::new(LogEventProvider::new());
+    let handle = layer.handle();
+    let subscriber = Registry::default()
+        .with(layer.with_filter(filter_fn(|metadata| metadata.name() != "funkabloid")));
+    tracing::subscriber::with_default(subscriber, tracing1::run);
+    let value = serde_json::to_value(handle.burn()).unwrap();
+    assert_eq!(value["1"]["v"]["name"], "hello!");
+    assert_eq!(value["1"]["1"]["v"]["ctx"]["message"], "hello world!");
+    assert_eq!(value["1"]["2"]["v"]["level"], "ERROR");
+
+    // span timing is recorded through the layer too
+    let span_time = &value["1"]["v"]["time"];
+    assert!(!span_time["entered"].is_null());
+    assert!(!span_time["closed"].is_null());
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn scoped_threads() {
+    use std::sync::Barrier;
+
+    use tracing::{info, info_span};
+
+    use crate::{providers::log::LogEventProvider, run_forest};
+
+    let barrier = Barrier::new(2);
+    let ash_trayce = run_forest(LogEventProvider::new(), |scope| {
+        info!("before");
+        let outer = info_span!("outer").entered();
+        let handle = scope.spawn(|scope| {
+            barrier.wait();
+            info!("in thread");
+            let _inner = info_span!("inner").entered();
+            scope.spawn(|_| info!("in nested thread")).join().unwrap();
+            barrier.wait();
+            // wait until `outer` has been dropped on the other thread
+            barrier.wait();
+            info!("after outer was dropped");
+        });
+        // the spawned thread does not affect the current one
+        info!("in outer");
+        barrier.wait();
+        barrier.wait();
+        drop(outer);
+        info!("after");
+        barrier.wait();
+        handle.join().unwrap();
+
+        // threads spawned outside of any span are placed at root
+        scope.spawn(|_| info!("root thread")).join().unwrap();
+    });
+
+    let value = serde_json::to_value(&ash_trayce).unwrap();
+    println!("{value:#}");
+    let message = |v: &serde_json::Value| v["v"]["ctx"]["message"].as_str().unwrap().to_owned();
+    assert_eq!(message(&value["0"]), "before");
+    let outer = &value["1"];
+    assert_eq!(outer["v"]["name"], "outer");
+    assert_eq!(message(&outer["0"]), "in outer");
+    assert_eq!(message(&outer["1"]), "in thread");
+    let inner = &outer["2"];
+    assert_eq!(inner["v"]["name"], "inner");
+    assert_eq!(message(&inner["0"]), "in nested thread");
+    assert_eq!(message(&inner["1"]), "after outer was dropped");
+    assert_eq!(message(&value["2"]), "after");
+    assert_eq!(message(&value["3"]), "root thread");
+
+    // `outer` was kept open until the spawned thread finished
+    let outer_closed = outer["v"]["time"]["closed"].as_u64().unwrap();
+    let inner_closed = inner["v"]["time"]["closed"].as_u64().unwrap();
+    assert!(outer_closed >= inner_closed);
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn thread_identity() {
+    use std::thread;
+
+    use serde_json::json;
+    use tracing::{dispatcher, info};
+
+    use crate::{providers::log::LogEventProvider, run_forest};
+
+    let ash_trayce = run_forest(LogEventProvider::new(), |scope| {
+        info!("first");
+        scope.spawn(|_| info!("unnamed")).join().unwrap();
+        let dispatch = dispatcher::get_default(Clone::clone);
+        thread::Builder::new()
+            .name("worker".to_owned())
+            .spawn_scoped(scope.thread_scope(), move || {
+                dispatcher::with_default(&dispatch, || info!("named"))
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        info!("last");
+    });
+
+    let threads = &ash_trayce.provider.threads;
+    assert_eq!(threads.len(), 3);
+    assert_eq!(threads[0].id, thread::current().id());
+    assert_eq!(threads[1].name, None);
+    assert_eq!(threads[2].name.as_deref(), Some("worker"));
+
+    let value = serde_json::to_value(&ash_trayce).unwrap();
+    println!("{value:#}");
+    let thread = |i: &str| value[i]["v"]["thread"].clone();
+    assert_eq!(
+        thread("0"),
+        json!({ "id": 0, "name": thread::current().name() })
+    );
+    assert_eq!(thread("1"), json!({ "id": 1, "name": null }));
+    assert_eq!(thread("2"), json!({ "id": 2, "name": "worker" }));
+    assert_eq!(thread("3"), thread("0"));
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn buffered_events() {
+    use std::sync::Barrier;
+
+    use forrust_fire_tree::fire::ForestFire;
+    use tracing::{Dispatch, info, info_span};
+
+    use crate::{
+        EventProvider, ForestFireSubscriber, providers::log::LogEventProvider, run_forest,
+    };
+
+    // buffering is opt-in
+    assert!(LogEventProvider::new().fork().is_none());
+
+    let barrier = Barrier::new(2);
+    let ash_trayce = run_forest(LogEventProvider::new().with_buffering(), |scope| {
+        let _work = info_span!("work").entered();
+        let handle = scope.spawn(|_| {
+            info!(n = 1, "first");
+            barrier.wait();
+            barrier.wait();
+            info!(n = 3, "third");
+        });
+        barrier.wait();
+        info!(n = 2, "second");
+        barrier.wait();
+        // places the spawned thread's events as it exits
+        handle.join().unwrap();
+        // places "second" before the span
+        info_span!("done").in_scope(|| info!(n = 4, "fourth"));
+    });
+
+    let value = serde_json::to_value(&ash_trayce).unwrap();
+    println!("{value:#}");
+    let work = &value["0"];
+    assert_eq!(work["v"]["name"], "work");
+    // events are placed in order per thread, with their own fields
+    let events = [("first", 1), ("third", 3), ("second", 2)];
+    for (i, (message, n)) in events.into_iter().enumerate() {
+        let event = &work[i.to_string()]["v"];
+        assert_eq!(event["ctx"]["message"], message);
+        assert_eq!(event["ctx"]["n"], n);
+    }
+    let id = |i: usize| work[i.to_string()]["v"]["id"].as_u64().unwrap();
+    assert!(id(0) < id(1) && id(1) < id(2));
+    assert_eq!(work["0"]["v"]["thread"], work["1"]["v"]["thread"]);
+    assert_ne!(work["0"]["v"]["thread"], work["2"]["v"]["thread"]);
+    assert_eq!(work["2"]["v"]["thread"], work["v"]["thread"]);
+    let done = &work["3"];
+    assert_eq!(done["v"]["name"], "done");
+    assert_eq!(done["0"]["v"]["ctx"]["n"], 4);
+    assert_eq!(ash_trayce.provider.threads.len(), 2);
+
+    // buffered events are counted before they are placed
+    let dispatch = Dispatch::new(ForestFireSubscriber::new(
+        ForestFire::new(),
+        LogEventProvider::new().with_buffering(),
+    ));
+    tracing::dispatcher::with_default(&dispatch, || {
+        info!("buffered");
+        info!("buffered too");
+    });
+    let subscriber = dispatch
+        .downcast_ref::<ForestFireSubscriber<LogEventProvider>>()
+        .unwrap();
+    assert_eq!(subscriber.node_count(), 2);
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn async_spans() {
+    use std::{
+        future::Future,
+        pin::Pin,
+        task::{Context, Poll, Waker},
+    };
+
+    use tracing::{Instrument, dispatcher, info, info_span};
+
+    use crate::{providers::log::LogEventProvider, run_forest};
+
+    /// Returns `Pending` once, so that other futures get polled in between.
+    async fn yield_now() {
+        let mut yielded = false;
+        std::future::poll_fn(|_| {
+            if yielded {
+                Poll::Ready(())
+            } else {
+                yielded = true;
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    type Task = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+    /// Polls every task once, dropping the finished ones.
+    fn poll_all(tasks: &mut Vec<Task>) {
+        let mut cx = Context::from_waker(Waker::noop());
+        tasks.retain_mut(|task| task.as_mut().poll(&mut cx).is_pending());
+    }
+
+    let ash_trayce = run_forest(LogEventProvider::new(), |scope| {
+        // interleaved on one thread
+        let mut tasks: Vec<Task> = (0..3)
+            .map(|i| {
+                let task = async move {
+                    info!("start");
+                    yield_now().await;
+                    info!("middle");
+                    yield_now().await;
+                    info!("end");
+                };
+                Box::pin(task.instrument(info_span!("task", i))) as Task
+            })
+            .collect();
+        while !tasks.is_empty() {
+            poll_all(&mut tasks);
+        }
+
+        // migrating between threads after every poll
+        let mut tasks = vec![Box::pin(
+            async {
+                info!("first poll");
+                yield_now().await;
+                info!("second poll");
+                yield_now().await;
+                info!("third poll");
+            }
+            .instrument(info_span!("migrating")),
+        ) as Task];
+        poll_all(&mut tasks);
+        let mut tasks = scope
+            .spawn(move |_| {
+                poll_all(&mut tasks);
+                tasks
+            })
+            .join()
+            .unwrap();
+        poll_all(&mut tasks);
+        assert!(tasks.is_empty());
+
+        // exited out of order
+        let a = info_span!("a");
+        let b = info_span!("b");
+        let entered_a = a.enter();
+        let entered_b = b.enter();
+        drop(entered_a);
+        info!("still in b");
+        drop(entered_b);
+        info!("in neither");
+
+        // exited on another thread
+        let c = info_span!("c");
+        let id = c.id().unwrap();
+        dispatcher::get_default(|dispatch| dispatch.enter(&id));
+        info!("in c");
+        // not spawned through `scope`, which would make `c` the thread's parent
+        let dispatch = dispatcher::get_default(Clone::clone);
+        std::thread::scope(|s| {
+            s.spawn(|| dispatch.exit(&id));
+        });
+        info!("not in c");
+    });
+
+    let value = serde_json::to_value(&ash_trayce).unwrap();
+    println!("{value:#}");
+    let message = |v: &serde_json::Value| v["v"]["ctx"]["message"].as_str().unwrap().to_owned();
+    for i in 0..3 {
+        let task = &value[i.to_string()];
+        assert_eq!(task["v"]["ctx"]["i"], i);
+        assert_eq!(message(&task["0"]), "start");
+        assert_eq!(message(&task["1"]), "middle");
+        assert_eq!(message(&task["2"]), "end");
+        assert!(task["v"]["time"]["closed"].is_u64());
+    }
+    let migrating = &value["3"];
+    assert_eq!(migrating["v"]["name"], "migrating");
+    assert_eq!(message(&migrating["0"]), "first poll");
+    assert_eq!(message(&migrating["1"]), "second poll");
+    assert_eq!(message(&migrating["2"]), "third poll");
+    assert_ne!(migrating["1"]["v"]["thread"], migrating["0"]["v"]["thread"]);
+
+    assert_eq!(value["4"]["v"]["name"], "a");
+    assert_eq!(message(&value["5"]["0"]), "still in b");
+    assert_eq!(message(&value["6"]), "in neither");
+    assert_eq!(message(&value["7"]["0"]), "in c");
+    assert_eq!(message(&value["8"]), "not in c");
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn budget() {
+    use forrust_fire_tree::fire::ForestFire;
+    use tracing::{error, info, info_span, warn};
+
+    use crate::{
+        ForestFireSubscriber,
+        budget::{Budget, OverflowPolicy},
+        nothread_run_subscriber_ret,
+        providers::log::LogEventProvider,
+    };
+
+    fn run(budget: Budget) -> serde_json::Value {
+        let subscriber = ForestFireSubscriber::new(ForestFire::new(), LogEventProvider::new())
+            .with_budget(budget);
+        let ((), ash_trayce) = nothread_run_subscriber_ret(subscriber, || {
+            let outer = info_span!("outer").entered();
+            info!("first");
+            // budget runs out here
+            info!("second");
+            info_span!("new span").in_scope(|| {
+                info!("in new span");
+                warn!("warning in new span");
+            });
+            drop(outer);
+            info_span!("new root span").in_scope(|| info!("in new root span"));
+            error!("error at root");
+        });
+        let total = ash_trayce.dropped.total();
+        let mut value = serde_json::to_value(ash_trayce).unwrap();
+        strip_volatile(&mut value);
+        println!("{value:#}");
+        assert_eq!(total, 9 - value.to_string().matches("\"v\":").count());
+        value
+    }
+    let message = |v: &serde_json::Value| v["v"]["ctx"]["message"].as_str().unwrap().to_owned();
+
+    let value = run(Budget::new(OverflowPolicy::Stop).max_nodes(2));
+    assert_eq!(message(&value["0"]["0"]), "first");
+    assert!(value["0"].get("1").is_none());
+    assert_eq!(value["0"]["dropped"], 4);
+    assert_eq!(value["dropped"], 3);
+    assert!(value.get("1").is_none());
+
+    let value = run(Budget::new(OverflowPolicy::KeepSevere).max_nodes(2));
+    // moved up out of the dropped span
+    assert_eq!(message(&value["0"]["1"]), "warning in new span");
+    assert_eq!(value["0"]["dropped"], 3);
+    assert_eq!(message(&value["1"]), "error at root");
+    assert_eq!(value["dropped"], 2);
+
+    let value = run(Budget::new(OverflowPolicy::DropSubtrees).max_nodes(2));
+    assert_eq!(message(&value["0"]["1"]), "second");
+    assert_eq!(value["0"]["2"]["v"]["name"], "new span");
+    assert_eq!(message(&value["0"]["2"]["1"]), "warning in new span");
+    assert!(value["0"].get("dropped").is_none());
+    assert!(value.get("1").is_none());
+    assert_eq!(value["dropped"], 3);
+
+    // the message "first" is 5 bytes long
+    let value = run(Budget::new(OverflowPolicy::Stop).max_bytes(5));
+    assert_eq!(message(&value["0"]["0"]), "first");
+    assert_eq!(value["0"]["dropped"], 4);
+
+    let value = run(Budget::new(OverflowPolicy::Stop));
+    assert!(value.get("dropped").is_none());
+    assert_eq!(message(&value["1"]["0"]), "in new root span");
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn ring_buffer() {
+    use forrust_fire_tree::fire::ForestFire;
+    use tracing::{info, info_span};
+
+    use crate::{
+        EventProvider, ForestFireSubscriber, budget::RingBuffer, nothread_run_subscriber_ret,
+        providers::log::LogEventProvider,
+    };
+
+    fn iterations(ring_buffer: RingBuffer, n: usize) -> crate::providers::log::LogAshes {
+        let subscriber = ForestFireSubscriber::new(ForestFire::new(), LogEventProvider::new())
+            .with_ring_buffer(ring_buffer);
+        let ((), ash_trayce) = nothread_run_subscriber_ret(subscriber, || {
+            let mut previous = None;
+            let mut outliving = Vec::new();
+            for i in 0..n {
+                let iteration = info_span!("iteration", i);
+                if let Some(previous) = &previous {
+                    iteration.follows_from(previous);
+                }
+                iteration.in_scope(|| {
+                    info!(i, "working");
+                    outliving.push(info_span!("outliving", i));
+                });
+                previous = iteration.id();
+            }
+            // these outlived their root-level spans, so the first one was evicted while open
+            outliving[0].in_scope(|| {
+                info!("discarded");
+                info_span!("discarded").in_scope(|| info!("discarded"));
+            });
+            outliving[n - 1].in_scope(|| info!("kept"));
+            drop(outliving);
+            info!("at root");
+        });
+        ash_trayce
+    }
+    let message = |v: &serde_json::Value| v["v"]["ctx"]["message"].as_str().unwrap().to_owned();
+
+    let ash_trayce = iterations(RingBuffer::subtrees(3), 10);
+    let value = serde_json::to_value(&ash_trayce).unwrap();
+    println!("{value:#}");
+    // the last two iterations and the root-level event
+    assert_eq!(ash_trayce.ash.root().n_children(), 3);
+    assert_eq!(value["0"]["v"]["ctx"]["i"], 8);
+    assert_eq!(value["1"]["v"]["ctx"]["i"], 9);
+    assert_eq!(message(&value["1"]["1"]["0"]), "kept");
+    assert_eq!(message(&value["2"]), "at root");
+    // links to evicted spans are gone
+    let (first, second) = (value["0"]["v"]["id"].clone(), value["1"]["v"]["id"].clone());
+    assert_eq!(
+        value["follows_from"],
+        serde_json::json!({ second.to_string(): [first] })
+    );
+    // only the remaining nodes' string values are left, numbers aren't stored as strings
+    let remaining = ["working", "working", "kept", "at root"].concat();
+    assert_eq!(ash_trayce.provider.used_bytes(), remaining.len());
+    assert!(ash_trayce.provider.string.len() < 2 * remaining.len());
+
+    // each iteration takes up 7 bytes. the last three don't fit, and once "at root" is
+    // recorded, neither do the last two
+    let ash_trayce = iterations(RingBuffer::bytes(20), 10);
+    let value = serde_json::to_value(&ash_trayce).unwrap();
+    assert_eq!(ash_trayce.ash.root().n_children(), 2);
+    assert_eq!(value["0"]["v"]["ctx"]["i"], 9);
+    assert!(ash_trayce.provider.used_bytes() <= 20);
+}
+
+#[test]
+fn panic_target() {
+    use tracing::error_span;
+
+    use crate::{
+        panic::{self, PANIC_TARGET},
+        providers::{ProviderExt, log::LogEventProvider},
+    };
+
+    // only events recorded by the hook count as panics, not anything with its target
+    LogEventProvider::new().run(|| {
+        let span = error_span!(target: PANIC_TARGET, "not a panic");
+        assert!(!panic::is_panic(span.metadata().unwrap()));
+    });
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn unwound_spans() {
+    use std::panic::catch_unwind;
+
+    use tracing::info_span;
+
+    use crate::providers::{ProviderExt, log::LogEventProvider};
+
+    let log_ashes = LogEventProvider::new().run(|| {
+        let _root = info_span!("root").entered();
+        info_span!("fine").in_scope(|| {});
+        let _ = catch_unwind(|| {
+            let _outer = info_span!("outer").entered();
+            info_span!("inner").in_scope(|| panic!("boom"));
+        });
+    });
+
+    let value = serde_json::to_value(&log_ashes).unwrap();
+    println!("{value:#}");
+    let root = &value["0"];
+    assert_eq!(root["v"].get("unwound"), None);
+    assert_eq!(root["0"]["v"]["name"], "fine");
+    assert_eq!(root["0"]["v"].get("unwound"), None);
+    // the path from the root to the panic
+    let outer = &root["1"];
+    assert_eq!(outer["v"]["name"], "outer");
+    assert_eq!(outer["v"]["unwound"], true);
+    assert_eq!(outer["0"]["v"]["name"], "inner");
+    assert_eq!(outer["0"]["v"]["unwound"], true);
+}
+
+#[test]
+#[cfg(feature = "html")]
+fn html_report() {
+    use tracing::{info, info_span};
+
+    use crate::{
+        html::write_html,
+        providers::{ProviderExt, log::LogEventProvider},
+    };
+
+    let log_ashes = LogEventProvider::new().run(|| {
+        let _span = info_span!("rendering").entered();
+        info!(markup = "</script><b>", "closing tags in strings");
+    });
+    let mut html = Vec::new();
+    write_html(&log_ashes, &mut html).unwrap();
+    let html = String::from_utf8(html).unwrap();
+
+    // everything is inlined
+    assert!(!html.contains("./index.ts"), "{html}");
+    assert!(!html.contains("./style.css"), "{html}");
+    assert!(html.ends_with("</body>\n\n</html>"), "{html}");
+    // nothing is loaded from the network: the only URLs are the targets of links, or in
+    // comments
+    for (i, _) in html.match_indices("http://").chain(html.match_indices("https://")) {
+        let before = &html[..i];
+        let tag = &before[before.rfind('<').unwrap()..];
+        let is_link = tag.starts_with("<a") && tag.ends_with("href=\"");
+        let is_comment = before.rfind("/*") > before.rfind("*/");
+        assert!(is_link || is_comment, "{}", &html[i..].lines().next().unwrap());
+    }
+
+    let (_, trace) = html
+        .split_once(r#"<script id="embedded-trace" type="application/json">"#)
+        .unwrap();
+    let (trace, _) = trace.split_once("</script>").unwrap();
+    assert!(!trace.contains('<'));
+    let trace: serde_json::Value = serde_json::from_str(trace).unwrap();
+    assert_eq!(trace, serde_json::to_value(&log_ashes).unwrap());
+    assert_eq!(trace["0"]["0"]["v"]["ctx"]["markup"], "</script><b>");
+}
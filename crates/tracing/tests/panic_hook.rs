@@ -0,0 +1,77 @@
+//! Tests for `PanicHook`, in a binary of their own since installing it replaces the panic
+//! hook of the whole process, which would record the panics of every other test as well.
+
+#![cfg(feature = "serde")]
+
+use std::{
+    fmt,
+    panic::{AssertUnwindSafe, catch_unwind},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use forrust_fire_tracing::{
+    panic::{PANIC_TARGET, PanicHook},
+    providers::{ProviderExt, log::LogEventProvider},
+    run_forest,
+};
+use tracing::{info, info_span, span};
+
+#[test]
+fn panics() {
+    PanicHook::new().backtrace(true).install();
+
+    let log_ashes = LogEventProvider::new().run(|| {
+        let _span = info_span!("risky").entered();
+        info!("before");
+        let _ = catch_unwind(|| panic!("oh no, {}", 42));
+    });
+    let value = serde_json::to_value(&log_ashes).unwrap();
+    println!("{value:#}");
+    let panic = &value["0"]["1"]["v"];
+    assert_eq!(panic["target"], PANIC_TARGET);
+    assert_eq!(panic["level"], "ERROR");
+    assert_eq!(panic["ctx"]["message"], "oh no, 42");
+    let location = panic["ctx"]["location"].as_str().unwrap();
+    assert!(location.starts_with(file!()), "{location}");
+    assert!(panic["ctx"]["backtrace"].is_string());
+
+    // panicking inside of the subscriber, which holds its lock there, neither deadlocks nor
+    // records anything
+    let log_ashes = LogEventProvider::new().run(|| {
+        // entered outside of `get_default`, which would hide the subscriber from the hook
+        let dispatch = tracing::dispatcher::get_default(tracing::Dispatch::clone);
+        let result = catch_unwind(AssertUnwindSafe(|| dispatch.enter(&span::Id::from_u64(99))));
+        assert!(result.is_err());
+    });
+    assert_eq!(log_ashes.ash.root().n_children(), 0);
+
+    // formatted while the subscriber is locked, holding the lock for a while
+    struct Slow(mpsc::Sender<()>);
+    impl fmt::Debug for Slow {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.send(()).unwrap();
+            thread::sleep(Duration::from_millis(200));
+            f.write_str("slow")
+        }
+    }
+
+    // a panic waits for the lock if another thread holds it
+    let ash_trayce = run_forest(LogEventProvider::new(), |scope| {
+        let (sender, receiver) = mpsc::channel();
+        scope.spawn(move |_| info!(slow = ?Slow(sender)));
+        receiver.recv().unwrap();
+        let _ = catch_unwind(|| panic!("while another thread records"));
+    });
+    let root = ash_trayce.ash.root();
+    assert_eq!(root.n_children(), 2);
+    let panics = root
+        .child_iter()
+        .filter(|&child| {
+            let payload = ash_trayce.ash.branch(child).payload().unwrap();
+            payload.metadata.target() == PANIC_TARGET
+        })
+        .count();
+    assert_eq!(panics, 1);
+}
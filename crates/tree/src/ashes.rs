@@ -61,6 +61,22 @@ impl<'a, T> BranchRef<'a, T> {
         }
     }
 
+    /// Returns the ID this branch had in the [`ForestFire`] it was [burned] from.
+    ///
+    /// Returns `None` if this is root, or if the branch did not come from a `ForestFire`
+    /// (for example, if it was deserialized).
+    ///
+    /// [`ForestFire`]: crate::fire::ForestFire
+    /// [burned]: crate::fire::ForestFire::burn
+    pub fn fire_id(self) -> Option<crate::fire::BranchId> {
+        match self.node {
+            Ok(node) if node.old_idx != usize::MAX => {
+                Some(crate::fire::BranchId::new_branch(node.old_idx))
+            }
+            _ => None,
+        }
+    }
+
     /// Returns an iterator of child IDs for this branch.
     pub fn child_iter(self) -> impl Iterator<Item = BranchId> {
         let range = Range::clone(match self.node {
@@ -173,7 +189,8 @@ pub(crate) struct Node<T> {
     pub(crate) children: Range<usize>,
     // note: do not rely on this being set!!
     //       the deserialization implementation simply sets this to usize::MAX
-    //       this field is only meant for the `ForestFire::burn` impl
+    //       this field is only meant for the `ForestFire::burn` impl and
+    //       `BranchRef::fire_id`
     pub(crate) old_idx: usize,
 }
 
@@ -2,7 +2,10 @@
 //!
 //! For serializing:
 //! - `Ashes` provides a [`Serialize`] implementation for payloads which also implement `Serialize`.
-//! - `Ashes` also provides the [`Ashes::serializable_with`] method for custom serialization.
+//! - `Ashes` also provides the [`Ashes::serializable_with`] method for custom serialization, and
+//!   [`Ashes::serializable_with_ctx`] for also adding extra information to each node.
+//! - Huge trees can be serialized in parts (limited by depth or number of children, or starting
+//!   from a subtree) through [`SerOptions`].
 //!
 //! For deserializing:
 //! - `Ashes` provides a [`Deserialize`] implementation for payloads which also implement `Deserialize`.
@@ -21,64 +24,360 @@ use std::{
 
 use serde::{
     Deserialize, Deserializer, Serialize, Serializer,
-    de::{self, DeserializeSeed, Error as _, Unexpected, Visitor},
+    de::{self, DeserializeSeed, Error as _, IgnoredAny, Unexpected, Visitor},
     ser::SerializeMap,
 };
 
 use crate::{
-    ashes::{Ashes, BranchId, BranchRef, Node},
+    ashes::{Ashes, BranchId, BranchRef, Node, children_len, nth_child},
     internal::serde::{ArrayFmt, USIZE_STR_MAX_CHARS},
 };
 
+mod entries;
+
 // todo: different format for non-human-readable serializers
 
-struct Ser<'a, T, S: Serialize, F: Copy + Fn(&'a T) -> S> {
+/// Information about a node being serialized through [`Ashes::serializable_with_ctx`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct NodeCtx<'a, T> {
+    /// The ID of the node.
+    pub id: BranchId,
+    /// A reference to the node.
+    pub branch: BranchRef<'a, T>,
+    /// The node's payload.
+    pub payload: &'a T,
+    /// The depth of the node within the tree. Children of root have a depth of `1`.
+    pub depth: usize,
+}
+
+impl<'a, T> Clone for NodeCtx<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for NodeCtx<'a, T> {}
+
+/// Defines which entries (other than the children) are serialized for each node.
+trait NodeSer<'a, T: 'a> {
+    /// The number of entries written by `serialize_entries`, if known.
+    fn len(&self) -> Option<usize>;
+    fn serialize_entries<M: SerializeMap>(
+        &self,
+        ctx: NodeCtx<'a, T>,
+        map: &mut M,
+    ) -> Result<(), M::Error>;
+}
+
+struct PayloadSer<F>(F);
+
+impl<'a, T: 'a, S: Serialize, F: Fn(&'a T) -> S> NodeSer<'a, T> for PayloadSer<F> {
+    fn len(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn serialize_entries<M: SerializeMap>(
+        &self,
+        ctx: NodeCtx<'a, T>,
+        map: &mut M,
+    ) -> Result<(), M::Error> {
+        map.serialize_entry("v", &(self.0)(ctx.payload))
+    }
+}
+
+struct CtxSer<F>(F);
+
+impl<'a, T: 'a, S: Serialize, F: Fn(NodeCtx<'a, T>) -> S> NodeSer<'a, T> for CtxSer<F> {
+    fn len(&self) -> Option<usize> {
+        None
+    }
+
+    fn serialize_entries<M: SerializeMap>(
+        &self,
+        ctx: NodeCtx<'a, T>,
+        map: &mut M,
+    ) -> Result<(), M::Error> {
+        (self.0)(ctx).serialize(entries::EntrySerializer { map })
+    }
+}
+
+/// Options for serializing only part of an [`Ashes`].
+///
+/// Used by [`Ashes::serializable_with_opts`] and [`Ashes::serializable_with_ctx_opts`].
+///
+/// Wherever nodes are left out, a marker node is placed instead of them. A marker node has
+/// no `v` key; instead, it has an `omitted` key with a map containing:
+/// - `nodes`: the total number of nodes left out (including all of their descendants).
+/// - `ids`: the [`BranchId`]s of the left out nodes whose parent _was_ serialized. Any of
+///   these can be used as [`start`] to serialize the missing part later on.
+///
+/// Children keep their original index as their key, so if some children are left out, the
+/// marker is placed at the index of the first one, and the remaining indices are skipped.
+///
+/// Such output is one-way: since marker nodes have no payload, the deserializers in this
+/// module reject them. To get a tree back, serialize it without any options.
+///
+/// [`start`]: SerOptions::start
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SerOptions {
+    /// The maximum depth of serialized nodes; deeper nodes are left out.
+    ///
+    /// Depth is counted from the top of the output: with the default [`start`] of root,
+    /// this is the depth within the tree (where children of root have a depth of `1`).
+    /// Otherwise, the `start` node has a depth of `1`.
+    ///
+    /// `None` (the default) means no limit.
+    ///
+    /// [`start`]: SerOptions::start
+    pub max_depth: Option<usize>,
+    /// The maximum number of children serialized for any single node.
+    ///
+    /// If a node has more children than `first + last`, only the first `first` and last
+    /// `last` children are serialized.
+    ///
+    /// `None` (the default) means no limit.
+    pub child_cap: Option<ChildCap>,
+    /// The node to start serializing from.
+    ///
+    /// If this is not [`BranchId::ROOT`], the output looks like a tree whose root has this
+    /// node as its only child.
+    pub start: BranchId,
+}
+
+/// A limit on the number of children serialized for a node; see [`SerOptions::child_cap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChildCap {
+    /// How many of the first children are serialized.
+    pub first: usize,
+    /// How many of the last children are serialized.
+    pub last: usize,
+}
+
+impl SerOptions {
+    /// Creates options which serialize the whole tree.
+    pub const fn new() -> Self {
+        Self {
+            max_depth: None,
+            child_cap: None,
+            start: BranchId::ROOT,
+        }
+    }
+}
+
+impl Default for SerOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A marker for left out nodes. See [`SerOptions`].
+struct Omitted<'a, T> {
     ashes: &'a Ashes<T>,
-    branch: BranchRef<'a, T>,
-    provider: F,
+    ids: Range<BranchId>,
+}
+
+impl<'a, T> Omitted<'a, T> {
+    fn node_count(&self) -> usize {
+        let mut count = 0;
+        let mut stack: Vec<Range<BranchId>> = vec![Range::clone(&self.ids)];
+        while let Some(range) = stack.pop() {
+            count += children_len(Range::clone(&range));
+            for id in range.start.value()..range.end.value() {
+                let children = self.ashes.branch(BranchId::new_branch(id)).children();
+                if !children.is_empty() {
+                    stack.push(children);
+                }
+            }
+        }
+        count
+    }
+}
+
+impl<'a, T> Serialize for Omitted<'a, T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        struct Ids(Range<BranchId>);
+        impl Serialize for Ids {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                serializer.collect_seq(self.0.start.value()..self.0.end.value())
+            }
+        }
+        struct Info<'a, 'b, T>(&'b Omitted<'a, T>);
+        impl<'a, 'b, T> Serialize for Info<'a, 'b, T> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("nodes", &self.0.node_count())?;
+                map.serialize_entry("ids", &Ids(Range::clone(&self.0.ids)))?;
+                map.end()
+            }
+        }
+
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("omitted", &Info(self))?;
+        map.end()
+    }
+}
+
+fn child_key(i: usize) -> ArrayFmt<USIZE_STR_MAX_CHARS> {
+    let mut arr = ArrayFmt::<USIZE_STR_MAX_CHARS>::new();
+    write!(arr, "{i}").expect("writing usize to sufficiently-sized buffer should never fail");
+    arr
 }
 
-impl<'a, T, S: Serialize, F: Copy + Fn(&'a T) -> S> Serialize for Ser<'a, T, S, F> {
+struct Ser<'a, 'n, T, N: NodeSer<'a, T>> {
+    ashes: &'a Ashes<T>,
+    id: BranchId,
+    depth: usize,
+    // depth within the output, only different from `depth` when not starting from root
+    out_depth: usize,
+    node: &'n N,
+    opts: &'n SerOptions,
+}
+
+impl<'a, 'n, T, N: NodeSer<'a, T>> Serialize for Ser<'a, 'n, T, N> {
     fn serialize<SS>(&self, serializer: SS) -> Result<SS::Ok, SS::Error>
     where
         SS: Serializer,
     {
-        let mut n = self.branch.n_children();
-        if self.branch.payload().is_some() {
-            n += 1;
-        }
+        let branch = self.ashes.branch(self.id);
+        let ctx = branch.payload().map(|payload| NodeCtx {
+            id: self.id,
+            branch,
+            payload,
+            depth: self.depth,
+        });
+
+        let children = branch.children();
+        let n_children = children_len(Range::clone(&children));
+        // (kept at the start, omitted, kept at the end)
+        let (first, omitted, last) = if n_children == 0 {
+            (0, 0, 0)
+        } else if self.opts.max_depth.is_some_and(|max| self.out_depth >= max) {
+            (0, n_children, 0)
+        } else {
+            match self.opts.child_cap {
+                Some(cap) if n_children > cap.first.saturating_add(cap.last) => {
+                    (cap.first, n_children - cap.first - cap.last, cap.last)
+                }
+                _ => (n_children, 0, 0),
+            }
+        };
+        let n_entries = first + last + usize::from(omitted != 0);
+
+        let n = match ctx {
+            Some(_) => self.node.len().map(|len| len + n_entries),
+            None => Some(n_entries),
+        };
 
-        let mut seq = serializer.serialize_map(Some(n))?;
-        if let Some(payload) = self.branch.payload() {
-            let payload = (self.provider)(payload);
-            seq.serialize_entry("v", &payload)?;
+        let mut seq = serializer.serialize_map(n)?;
+        if let Some(ctx) = ctx {
+            self.node.serialize_entries(ctx, &mut seq)?;
         }
-        for (i, child) in self.branch.child_iter().enumerate() {
-            let mut arr = ArrayFmt::<USIZE_STR_MAX_CHARS>::new();
-            write!(arr, "{i}")
-                .expect("writing usize to sufficiently-sized buffer should never fail");
+        let child = |i: usize| Ser {
+            ashes: self.ashes,
+            id: nth_child(Range::clone(&children), i),
+            depth: self.depth.saturating_add(1),
+            out_depth: self.out_depth.saturating_add(1),
+            node: self.node,
+            opts: self.opts,
+        };
+        for i in 0..first {
+            seq.serialize_entry(child_key(i).str(), &child(i))?;
+        }
+        if omitted != 0 {
+            let ids = nth_child(Range::clone(&children), first)
+                ..BranchId::new_branch(children.end.value() - last);
             seq.serialize_entry(
-                arr.str(),
-                &Ser {
+                child_key(first).str(),
+                &Omitted {
                     ashes: self.ashes,
-                    branch: self.ashes.branch(child),
-                    provider: self.provider,
+                    ids,
                 },
             )?;
         }
+        for i in (n_children - last)..n_children {
+            seq.serialize_entry(child_key(i).str(), &child(i))?;
+        }
         seq.end()
     }
 }
 
+struct RootSer<'a, T, N: NodeSer<'a, T>> {
+    ashes: &'a Ashes<T>,
+    node: N,
+    opts: SerOptions,
+}
+
+impl<'a, T, N: NodeSer<'a, T>> Serialize for RootSer<'a, T, N> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let start = self.opts.start;
+        if start.is_root() {
+            return Ser {
+                ashes: self.ashes,
+                id: BranchId::ROOT,
+                depth: 0,
+                out_depth: 0,
+                node: &self.node,
+                opts: &self.opts,
+            }
+            .serialize(serializer);
+        }
+
+        let mut map = serializer.serialize_map(Some(1))?;
+        if self.opts.max_depth == Some(0) {
+            map.serialize_entry(
+                "0",
+                &Omitted {
+                    ashes: self.ashes,
+                    ids: start..BranchId::new_branch(start.value() + 1),
+                },
+            )?;
+        } else {
+            let mut depth = 1;
+            let mut parent = self.ashes.branch(start).parent();
+            while let Some(id) = parent.filter(|id| !id.is_root()) {
+                depth += 1;
+                parent = self.ashes.branch(id).parent();
+            }
+
+            map.serialize_entry(
+                "0",
+                &Ser {
+                    ashes: self.ashes,
+                    id: start,
+                    depth,
+                    out_depth: 1,
+                    node: &self.node,
+                    opts: &self.opts,
+                },
+            )?;
+        }
+        map.end()
+    }
+}
+
 impl<T: Serialize> Serialize for Ashes<T> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        Ser {
+        RootSer {
             ashes: self,
-            branch: self.root(),
-            provider: identity,
+            node: PayloadSer(identity),
+            opts: SerOptions::new(),
         }
         .serialize(serializer)
     }
@@ -353,6 +652,8 @@ impl<'de, 'a, T, Sub: DeserializeSeed<'de, Value = T> + Clone, Ty: DeserTy<T>> V
         enum Key {
             Payload,
             Child(usize),
+            // extra information placed next to the children (see `serializable_with_ctx`)
+            Other,
         }
         struct KeyVisitor;
         impl<'de> Visitor<'de> for KeyVisitor {
@@ -361,7 +662,7 @@ impl<'de, 'a, T, Sub: DeserializeSeed<'de, Value = T> + Clone, Ty: DeserTy<T>> V
             fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
                 write!(
                     formatter,
-                    "a string representing a number or the string 'v'"
+                    "a string representing a number, the string 'v' or some other non-numeric key"
                 )
             }
 
@@ -373,8 +674,11 @@ impl<'de, 'a, T, Sub: DeserializeSeed<'de, Value = T> + Clone, Ty: DeserTy<T>> V
                     Ok(Key::Payload)
                 } else if let Ok(idx) = str.parse::<usize>() {
                     Ok(Key::Child(idx))
-                } else {
+                } else if str.starts_with(|c: char| c.is_ascii_digit()) {
+                    // almost a child, likely a mistake
                     Err(E::invalid_value(Unexpected::Str(str), &self))
+                } else {
+                    Ok(Key::Other)
                 }
             }
         }
@@ -401,6 +705,9 @@ impl<'de, 'a, T, Sub: DeserializeSeed<'de, Value = T> + Clone, Ty: DeserTy<T>> V
 
                     payload = Some(map.next_value_seed(self.sub.clone())?);
                 }
+                Key::Other => {
+                    map.next_value::<IgnoredAny>()?;
+                }
                 Key::Child(i) => {
                     let sub_start = self.storage.entry_stack.len();
                     let sub: DeserSeed<'_, '_, _, _, DeserChild<T>> = DeserSeed {
@@ -469,10 +776,118 @@ impl<T> Ashes<T> {
         F: Copy + 'a + Fn(&'a T) -> S,
         S: Serialize + 'a,
     {
-        Ser {
+        self.serializable_with_opts(SerOptions::new(), provider)
+    }
+
+    /// Returns a serializable object which uses the `provider` function to retrieve
+    /// objects by which to serialize instances of `T`, serializing only the parts of the
+    /// tree selected by `opts`.
+    ///
+    /// See [`SerOptions`] for the format of the left out parts. For serializing payloads
+    /// which implement `Serialize`, simply pass `|v| v` as the `provider`.
+    ///
+    /// # Panics
+    ///
+    /// Serializing panics if [`SerOptions::start`] is not an [existing](Self::exists) branch.
+    pub fn serializable_with_opts<'a, S, F>(
+        &'a self,
+        opts: SerOptions,
+        provider: F,
+    ) -> impl Serialize + 'a
+    where
+        F: 'a + Fn(&'a T) -> S,
+        S: Serialize + 'a,
+    {
+        RootSer {
+            ashes: self,
+            node: PayloadSer(provider),
+            opts,
+        }
+    }
+
+    /// Returns a serializable object which uses the `provider` function to retrieve the
+    /// entries of each node, given the node's [context](NodeCtx).
+    ///
+    /// The object returned by `provider` must serialize as a map or a struct: its entries are
+    /// placed in the node's map, next to the node's children. To stay compatible with the
+    /// format used by [`Ashes`]' `Serialize` implementation, it should contain the payload
+    /// under the `v` key; any other keys may be used for extra information, with the
+    /// exception of keys which are numbers (those are reserved for children). The
+    /// deserializers in this module skip over any such extra keys.
+    ///
+    /// `provider` is not called for the root node.
+    ///
+    /// # Examples
+    ///
+    /// With `serde_json`:
+    /// ```
+    /// use forrust_fire_tree::fire::{BranchId, ForestFire};
+    /// use serde::Serialize;
+    ///
+    /// let mut fire = ForestFire::new();
+    /// let a = fire.branch(BranchId::ROOT, "a");
+    /// fire.branch(a, "b");
+    /// let ashes = fire.burn();
+    ///
+    /// #[derive(Serialize)]
+    /// struct Node<'a> {
+    ///     v: &'a str,
+    ///     id: usize,
+    ///     depth: usize,
+    /// }
+    ///
+    /// let value = serde_json::to_value(ashes.serializable_with_ctx(|ctx| Node {
+    ///     v: ctx.payload,
+    ///     id: ctx.id.value(),
+    ///     depth: ctx.depth,
+    /// }))
+    /// .unwrap();
+    /// assert_eq!(
+    ///     value,
+    ///     serde_json::json!({
+    ///         "0": {
+    ///             "v": "a",
+    ///             "id": 1,
+    ///             "depth": 1,
+    ///             "0": { "v": "b", "id": 0, "depth": 2 }
+    ///         }
+    ///     })
+    /// );
+    /// ```
+    pub fn serializable_with_ctx<'a, S, F>(&'a self, provider: F) -> impl Serialize + 'a
+    where
+        F: 'a + Fn(NodeCtx<'a, T>) -> S,
+        S: Serialize + 'a,
+    {
+        self.serializable_with_ctx_opts(SerOptions::new(), provider)
+    }
+
+    /// Returns a serializable object which uses the `provider` function to retrieve the
+    /// entries of each node, given the node's [context](NodeCtx), serializing only the
+    /// parts of the tree selected by `opts`.
+    ///
+    /// See [`serializable_with_ctx`] for how `provider` is used, and [`SerOptions`] for the
+    /// format of the left out parts. [`NodeCtx::depth`] is always the depth within the whole
+    /// tree, regardless of [`SerOptions::start`].
+    ///
+    /// # Panics
+    ///
+    /// Serializing panics if [`SerOptions::start`] is not an [existing](Self::exists) branch.
+    ///
+    /// [`serializable_with_ctx`]: Self::serializable_with_ctx
+    pub fn serializable_with_ctx_opts<'a, S, F>(
+        &'a self,
+        opts: SerOptions,
+        provider: F,
+    ) -> impl Serialize + 'a
+    where
+        F: 'a + Fn(NodeCtx<'a, T>) -> S,
+        S: Serialize + 'a,
+    {
+        RootSer {
             ashes: self,
-            branch: self.root(),
-            provider,
+            node: CtxSer(provider),
+            opts,
         }
     }
 }
@@ -7,6 +7,9 @@ use crate::{
     internal::BranchIdImpl,
 };
 
+#[cfg(feature = "serde")]
+pub mod serde;
+
 define_branch_id!(
     /// The ID for some branch of a [`ForestFire`].
     ///
@@ -16,6 +19,7 @@ define_branch_id!(
     struct BranchId
 );
 
+#[derive(Debug, Clone)]
 struct Node<T> {
     parent: BranchId,
     payload: T,
@@ -32,9 +36,20 @@ struct Node<T> {
 /// "payload". The one exception is the root node, which can never have any
 /// payloads.
 ///
+/// Whole subtrees can be [removed] to reclaim their memory, which keeps the
+/// branch IDs of all other nodes intact.
+///
 /// [burned]: Self::burn
+/// [removed]: Self::remove_subtrees
+#[derive(Debug, Clone)]
 pub struct ForestFire<T> {
+    // in order of creation, so that children stay in insertion order
     nodes: Vec<Node<T>>,
+    // the branch ID of each node in `nodes`. only filled in once nodes have been
+    // removed, until then a node's branch ID is its index
+    ids: Vec<usize>,
+    // how many nodes have been removed
+    removed: usize,
 }
 
 const _: () = {
@@ -52,21 +67,58 @@ fn root_panic() -> ! {
 impl<T> ForestFire<T> {
     /// Constructs a new, empty `ForestFire<T>`.
     pub const fn new() -> Self {
-        Self { nodes: Vec::new() }
+        Self {
+            nodes: Vec::new(),
+            ids: Vec::new(),
+            removed: 0,
+        }
+    }
+
+    /// Returns the index within `nodes` of a branch, or `None` if it does not exist.
+    fn index(&self, branch: BranchId) -> Option<usize> {
+        if self.removed == 0 {
+            (branch.value() < self.nodes.len()).then_some(branch.value())
+        } else {
+            self.ids.binary_search(&branch.value()).ok()
+        }
+    }
+
+    fn node(&self, branch: BranchId) -> &Node<T> {
+        match self.index(branch) {
+            Some(idx) => &self.nodes[idx],
+            None => branch.indexing_panic(),
+        }
+    }
+
+    fn node_mut(&mut self, branch: BranchId) -> &mut Node<T> {
+        match self.index(branch) {
+            Some(idx) => &mut self.nodes[idx],
+            None => branch.indexing_panic(),
+        }
     }
 
     /// Returns the number of nodes in this tree.
     ///
-    /// This does not include the root node.
+    /// This does not include the root node, nor any [removed](Self::remove_subtrees) nodes.
     pub fn node_count(&self) -> usize {
         self.nodes.len()
     }
 
+    /// Reserves capacity for at least `additional` more nodes.
+    ///
+    /// See [`Vec::reserve`].
+    pub fn reserve(&mut self, additional: usize) {
+        self.nodes.reserve(additional);
+        if self.removed != 0 {
+            self.ids.reserve(additional);
+        }
+    }
+
     /// Checks whether there is a branch with the given branch ID.
     ///
-    /// Branch IDs given out by a `ForestFire` are valid for the entirety of that `ForestFire`'s lifetime,
-    /// so there is never a need to check if you're sure your branch ID came from this exact `ForestFire`
-    /// instance.
+    /// Branch IDs given out by a `ForestFire` are valid for the entirety of that `ForestFire`'s lifetime
+    /// (unless they are [removed](Self::remove_subtrees)), so there is never a need to check if you're
+    /// sure your branch ID came from this exact `ForestFire` instance.
     ///
     /// Always returns `true` for [`BranchId::ROOT`].
     pub fn exists(&self, branch: BranchId) -> bool {
@@ -74,7 +126,13 @@ impl<T> ForestFire<T> {
             return true;
         }
 
-        self.nodes.get(branch.value()).is_some()
+        self.index(branch).is_some()
+    }
+
+    /// Checks whether the given branch ID was given out by this `ForestFire`, but has since
+    /// been [removed](Self::remove_subtrees).
+    pub fn was_removed(&self, branch: BranchId) -> bool {
+        !branch.is_root() && branch.value() < self.next_id().value() && !self.exists(branch)
     }
 
     /// Returns the parent ID of a given branch, or `None` if it is [`BranchId::ROOT`].
@@ -86,10 +144,7 @@ impl<T> ForestFire<T> {
         if of.is_root() {
             None
         } else {
-            match self.nodes.get(of.value()) {
-                Some(node) => Some(node.parent),
-                None => of.indexing_panic(),
-            }
+            Some(self.node(of).parent)
         }
     }
 
@@ -104,13 +159,7 @@ impl<T> ForestFire<T> {
         if of.is_root() {
             None
         } else {
-            Some(
-                &self
-                    .nodes
-                    .get(of.value())
-                    .unwrap_or_else(|| of.indexing_panic())
-                    .payload,
-            )
+            Some(&self.node(of).payload)
         }
     }
 
@@ -126,13 +175,7 @@ impl<T> ForestFire<T> {
         if of.is_root() {
             None
         } else {
-            Some(
-                &mut self
-                    .nodes
-                    .get_mut(of.value())
-                    .unwrap_or_else(|| of.indexing_panic())
-                    .payload,
-            )
+            Some(&mut self.node_mut(of).payload)
         }
     }
 
@@ -175,7 +218,7 @@ impl<T> ForestFire<T> {
             parent.indexing_panic()
         }
 
-        let id = self.nodes.len();
+        let id = self.next_id().value();
 
         // since allocations are not allowed to exceed isize::MAX *bytes*, a Vec
         // will most definitely not reach usize::MAX *instances* as long as the
@@ -191,6 +234,9 @@ impl<T> ForestFire<T> {
         debug_assert_ne!(size_of::<Node<T>>(), 0);
 
         self.nodes.push(Node { parent, payload });
+        if self.removed != 0 {
+            self.ids.push(id);
+        }
 
         BranchId::new_branch(id)
     }
@@ -201,7 +247,88 @@ impl<T> ForestFire<T> {
     ///
     /// [`branch`]: Self::branch
     pub fn next_id(&self) -> BranchId {
-        BranchId::new_branch(self.nodes.len())
+        BranchId::new_branch(self.nodes.len() + self.removed)
+    }
+
+    /// Returns an iterator over every branch and its payload, in order of creation.
+    pub fn payloads_mut(&mut self) -> impl Iterator<Item = (BranchId, &mut T)> {
+        let (removed, ids) = (self.removed, &self.ids);
+        self.nodes.iter_mut().enumerate().map(move |(idx, node)| {
+            let id = if removed == 0 { idx } else { ids[idx] };
+            (BranchId::new_branch(id), &mut node.payload)
+        })
+    }
+
+    /// Removes the given branches along with all of their descendants, returning the removed
+    /// branches and their payloads in order of creation.
+    ///
+    /// The memory used by the removed nodes is reclaimed, while the branch IDs of the remaining
+    /// nodes stay valid. Removed branch IDs are never given out again, and [`was_removed`]
+    /// can be used to tell them apart from IDs which never existed.
+    ///
+    /// This goes over every node of the tree, so prefer removing many subtrees at once over
+    /// removing them one by one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of `branches` is not an [existing](Self::exists) branch, or if it is
+    /// [`BranchId::ROOT`].
+    ///
+    /// [`was_removed`]: Self::was_removed
+    pub fn remove_subtrees(&mut self, branches: &[BranchId]) -> Vec<(BranchId, T)> {
+        let mut is_removed = vec![false; self.nodes.len()];
+        for &branch in branches {
+            if branch.is_root() {
+                root_panic()
+            }
+            match self.index(branch) {
+                Some(idx) => is_removed[idx] = true,
+                None => branch.indexing_panic(),
+            }
+        }
+        if self.removed == 0 {
+            self.ids = (0..self.nodes.len()).collect();
+        }
+        // parents are always created before their children, so a single pass
+        // in order of creation finds every descendant
+        for idx in 0..self.nodes.len() {
+            let parent = self.nodes[idx].parent;
+            if !parent.is_root()
+                && let Some(parent) = self.index(parent)
+                && is_removed[parent]
+            {
+                is_removed[idx] = true;
+            }
+        }
+
+        let mut removed = Vec::new();
+        let mut kept = 0;
+        let nodes = std::mem::take(&mut self.nodes);
+        for (idx, node) in nodes.into_iter().enumerate() {
+            let id = self.ids[idx];
+            if is_removed[idx] {
+                removed.push((BranchId::new_branch(id), node.payload));
+            } else {
+                self.nodes.push(node);
+                self.ids[kept] = id;
+                kept += 1;
+            }
+        }
+        self.ids.truncate(kept);
+        self.removed += removed.len();
+        removed
+    }
+
+    /// Skips the next `n` branch IDs, as if they had been given out and then removed.
+    #[cfg(feature = "serde")]
+    fn skip_ids(&mut self, n: usize) {
+        if n == 0 {
+            return;
+        }
+        if self.removed == 0 {
+            self.ids = (0..self.nodes.len()).collect();
+        }
+        self.removed += n;
     }
 
     /// Finishes building this tree and creates an instance of [Ashes].
@@ -231,10 +358,19 @@ impl<T> ForestFire<T> {
             .enumerate()
             .map(|(i, Node { parent, payload })| AshNode {
                 // parent will use old-style indexing for now
-                parent: AshBranchId::new(parent.value()),
+                parent: if parent.is_root() || self.removed == 0 {
+                    AshBranchId::new(parent.value())
+                } else {
+                    AshBranchId::new_branch(
+                        self.ids
+                            .binary_search(&parent.value())
+                            .expect("parents of remaining nodes are never removed"),
+                    )
+                },
                 payload,
                 children: 0..0,
-                old_idx: i,
+                // the branch ID, which is in the same order as `i`
+                old_idx: if self.removed == 0 { i } else { self.ids[i] },
             })
             .collect();
 
@@ -0,0 +1,812 @@
+//! Tools for serializing & deserializing the [`ForestFire`] data structure.
+//!
+//! A `ForestFire` can be serialized in its native format:
+//! - `ForestFire` provides a [`Serialize`] implementation for payloads which also implement
+//!   `Serialize`, and the [`ForestFire::serializable_with`] method for custom serialization.
+//! - The native format is a sequence of nodes in order of their [`BranchId`], each being a map
+//!   with the keys `parent` (the branch ID of the node's parent, or `null` for children of
+//!   root) and `v` (the node's payload). A node's branch ID is simply its position, unless
+//!   nodes were [removed](ForestFire::remove_subtrees): then every node also has an `id` key
+//!   with its branch ID, and the sequence ends with a map whose only key is `next_id` (the
+//!   branch ID the next new node would get). Either way, branch IDs stay valid after a
+//!   roundtrip, and removed ones stay removed.
+//!
+//! A `ForestFire` can be loaded from three formats:
+//! - The native format described above. `ForestFire` provides a [`Deserialize`] implementation
+//!   for payloads which also implement `Deserialize`, which creates the same temporary
+//!   allocations as the one for [`Ashes`](crate::ashes::Ashes).
+//! - The tree format used by [`Ashes`](crate::ashes::Ashes) (see [`crate::ashes::serde`]).
+//! - The node list format, which is a sequence of nodes, each being a map with the keys:
+//!     - `id`: a unique `usize` identifying the node.
+//!     - `parent`: the `id` of the node's parent, or `null` for children of root. The parent
+//!       must appear before the node itself.
+//!     - `v`: the node's payload.
+//!
+//! All of these are handled by [`FireDeserStorage`].
+
+use std::{collections::HashMap, marker::PhantomData, ops::Range};
+
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, DeserializeSeed, Error as _, MapAccess, SeqAccess, Unexpected, Visitor},
+    ser::{SerializeMap, SerializeSeq},
+};
+
+use crate::{
+    ashes::serde::AshDeserStorage,
+    fire::{BranchId, ForestFire, Node},
+};
+
+struct SerNode<S> {
+    // only written once nodes have been removed
+    id: Option<usize>,
+    parent: BranchId,
+    payload: S,
+}
+
+impl<S: Serialize> Serialize for SerNode<S> {
+    fn serialize<SS>(&self, serializer: SS) -> Result<SS::Ok, SS::Error>
+    where
+        SS: Serializer,
+    {
+        let parent = if self.parent.is_root() {
+            None
+        } else {
+            Some(self.parent.value())
+        };
+
+        let mut map = serializer.serialize_map(Some(2 + usize::from(self.id.is_some())))?;
+        if let Some(id) = self.id {
+            map.serialize_entry("id", &id)?;
+        }
+        map.serialize_entry("parent", &parent)?;
+        map.serialize_entry("v", &self.payload)?;
+        map.end()
+    }
+}
+
+struct SerNextId(usize);
+
+impl Serialize for SerNextId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("next_id", &self.0)?;
+        map.end()
+    }
+}
+
+struct Ser<'a, T, S: Serialize, F: Fn(&'a T) -> S> {
+    fire: &'a ForestFire<T>,
+    provider: F,
+}
+
+impl<'a, T, S: Serialize, F: Fn(&'a T) -> S> Serialize for Ser<'a, T, S, F> {
+    fn serialize<SS>(&self, serializer: SS) -> Result<SS::Ok, SS::Error>
+    where
+        SS: Serializer,
+    {
+        let fire = self.fire;
+        // positions differ from branch IDs once nodes have been removed
+        let removed = fire.removed != 0;
+        let len = fire.nodes.len() + usize::from(removed);
+        let mut seq = serializer.serialize_seq(Some(len))?;
+        for (idx, Node { parent, payload }) in fire.nodes.iter().enumerate() {
+            seq.serialize_element(&SerNode {
+                id: removed.then(|| fire.ids[idx]),
+                parent: *parent,
+                payload: (self.provider)(payload),
+            })?;
+        }
+        if removed {
+            seq.serialize_element(&SerNextId(fire.next_id().value()))?;
+        }
+        seq.end()
+    }
+}
+
+impl<T: Serialize> Serialize for ForestFire<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Ser {
+            fire: self,
+            provider: |payload| payload,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<T> ForestFire<T> {
+    /// Returns a serializable object which uses the `provider` function to retrieve
+    /// objects by which to serialize instances of `T`.
+    ///
+    /// `ForestFire` itself implements `Serialize` for any `T` which also implements
+    /// `Serialize`, so this method is likely not what you want unless you're
+    /// implementing a custom serializer for `T`.
+    pub fn serializable_with<'a, S, F>(&'a self, provider: F) -> impl Serialize + 'a
+    where
+        F: 'a + Fn(&'a T) -> S,
+        S: Serialize + 'a,
+    {
+        Ser {
+            fire: self,
+            provider,
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for ForestFire<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut storage = FireDeserStorage::new();
+        storage.seed().deserialize(deserializer)?;
+        Ok(storage.fire)
+    }
+}
+
+/// A collection of buffers required for deserializing into a [`ForestFire`].
+///
+/// Unlike [`AshDeserStorage`], the output tree is _not_ cleared between deserializations:
+/// loaded nodes are added to [`fire`] under a parent of your choosing, so it may already
+/// contain nodes (or be recorded into after loading).
+///
+/// Every seed returns a mapping from each loaded node to the [`BranchId`] it was given in
+/// `fire`:
+/// - For the native format, a `Vec` with the new branch ID of each node, in order. Each
+///   node's serialized branch ID is offset by [`next_id`] of `fire` before loading, so if
+///   `fire` was empty, every node keeps its branch ID.
+/// - For the tree format, a `Vec` indexed by the node's position in a depth-first,
+///   pre-order walk of the tree (`0` is the first child of root, `1` is its first child,
+///   if any, and so on). Children are walked in order of their index.
+/// - For the node list format, a `HashMap` keyed by the node's `id`.
+///
+/// # Examples
+///
+/// With `serde_json`:
+/// ```
+/// use forrust_fire_tree::fire::{BranchId, serde::FireDeserStorage};
+/// use serde::de::DeserializeSeed;
+///
+/// let mut storage = FireDeserStorage::<u32>::new();
+/// let mut deserializer = serde_json::Deserializer::from_str(
+/// r#"[
+///     { "id": 10, "parent": null, "v": 1 },
+///     { "id": 4, "parent": 10, "v": 2 }
+/// ]"#);
+/// let ids = storage.node_list_seed(BranchId::ROOT).deserialize(&mut deserializer).unwrap();
+///
+/// // continue recording under the node which had the ID `4`
+/// storage.fire.branch(ids[&4], 3);
+/// let ashes = storage.fire.burn();
+///
+/// assert_eq!(
+///     serde_json::to_value(&ashes).unwrap(),
+///     serde_json::json!({
+///         "0": {
+///             "v": 1,
+///             "0": {
+///                 "v": 2,
+///                 "0": { "v": 3 }
+///             }
+///         }
+///     })
+/// );
+/// ```
+///
+/// [`fire`]: #structfield.fire
+/// [`next_id`]: ForestFire::next_id
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct FireDeserStorage<T> {
+    /// The [`ForestFire`] instance into which the nodes will be written.
+    ///
+    /// You are free to do anything with it (including taking or replacing it) inbetween
+    /// deserializations.
+    pub fire: ForestFire<T>,
+    ashes: AshDeserStorage<T>,
+    tree_stack: Vec<(usize, BranchId)>,
+}
+
+impl<T> FireDeserStorage<T> {
+    /// Creates a new `FireDeserStorage` with an empty tree.
+    pub fn new() -> Self {
+        Self::from_fire(ForestFire::new())
+    }
+
+    /// Creates a new `FireDeserStorage` which will add nodes to an existing tree.
+    pub fn from_fire(fire: ForestFire<T>) -> Self {
+        Self {
+            fire,
+            ashes: AshDeserStorage::new(),
+            tree_stack: Vec::new(),
+        }
+    }
+
+    /// Creates a new deserialization seed for the native format, using `Seed` for
+    /// deserializing payloads.
+    ///
+    /// Nodes are added after any nodes already in [`fire`], with children of the serialized
+    /// root also being children of root in `fire`. If `fire` is empty, every node keeps its
+    /// serialized branch ID, and [removed](ForestFire::remove_subtrees) branch IDs are skipped
+    /// again. The seed returns the mapping described in the [type documentation](Self).
+    ///
+    /// For `T`s which already implement `Deserialize`, it's simpler to use the [seed] method
+    /// instead.
+    ///
+    /// [seed]: #method.seed
+    /// [`fire`]: #structfield.fire
+    pub fn seed_with<'de, 'a, Seed: DeserializeSeed<'de, Value = T> + Clone>(
+        &'a mut self,
+        seed: Seed,
+    ) -> impl DeserializeSeed<'de, Value = Vec<BranchId>> {
+        NativeSeed {
+            storage: self,
+            sub: seed,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Creates a new deserialization seed for the native format.
+    ///
+    /// See [seed_with] for details.
+    ///
+    /// [seed_with]: #method.seed_with
+    pub fn seed<'de, 'a>(&'a mut self) -> impl DeserializeSeed<'de, Value = Vec<BranchId>>
+    where
+        T: Deserialize<'de>,
+    {
+        self.seed_with(PhantomData::<T>)
+    }
+
+    /// Creates a new deserialization seed for the tree format, using `Seed` for deserializing
+    /// payloads.
+    ///
+    /// The children of the deserialized root are added as children of `parent`. The seed
+    /// returns the pre-order mapping described in the [type documentation](Self).
+    ///
+    /// For `T`s which already implement `Deserialize`, it's simpler to use the [tree_seed]
+    /// method instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` is not an [existing](ForestFire::exists) branch of [`fire`].
+    ///
+    /// [tree_seed]: #method.tree_seed
+    /// [`fire`]: #structfield.fire
+    pub fn tree_seed_with<'de, 'a, Seed: DeserializeSeed<'de, Value = T> + Clone>(
+        &'a mut self,
+        seed: Seed,
+        parent: BranchId,
+    ) -> impl DeserializeSeed<'de, Value = Vec<BranchId>> {
+        if !self.fire.exists(parent) {
+            parent.indexing_panic()
+        }
+
+        TreeSeed {
+            storage: self,
+            sub: seed,
+            parent,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Creates a new deserialization seed for the tree format.
+    ///
+    /// See [tree_seed_with] for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` is not an [existing](ForestFire::exists) branch of [`fire`].
+    ///
+    /// [tree_seed_with]: #method.tree_seed_with
+    /// [`fire`]: #structfield.fire
+    pub fn tree_seed<'de, 'a>(
+        &'a mut self,
+        parent: BranchId,
+    ) -> impl DeserializeSeed<'de, Value = Vec<BranchId>>
+    where
+        T: Deserialize<'de>,
+    {
+        self.tree_seed_with(PhantomData::<T>, parent)
+    }
+
+    /// Creates a new deserialization seed for the node list format, using `Seed` for
+    /// deserializing payloads.
+    ///
+    /// Nodes with a `null` parent are added as children of `parent`. The seed returns a
+    /// mapping from every node's `id` to its new branch ID.
+    ///
+    /// For `T`s which already implement `Deserialize`, it's simpler to use the
+    /// [node_list_seed] method instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` is not an [existing](ForestFire::exists) branch of [`fire`].
+    ///
+    /// [node_list_seed]: #method.node_list_seed
+    /// [`fire`]: #structfield.fire
+    pub fn node_list_seed_with<'de, 'a, Seed: DeserializeSeed<'de, Value = T> + Clone>(
+        &'a mut self,
+        seed: Seed,
+        parent: BranchId,
+    ) -> impl DeserializeSeed<'de, Value = HashMap<usize, BranchId>> {
+        if !self.fire.exists(parent) {
+            parent.indexing_panic()
+        }
+
+        NodeListSeed {
+            storage: self,
+            sub: seed,
+            parent,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Creates a new deserialization seed for the node list format.
+    ///
+    /// See [node_list_seed_with] for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` is not an [existing](ForestFire::exists) branch of [`fire`].
+    ///
+    /// [node_list_seed_with]: #method.node_list_seed_with
+    /// [`fire`]: #structfield.fire
+    pub fn node_list_seed<'de, 'a>(
+        &'a mut self,
+        parent: BranchId,
+    ) -> impl DeserializeSeed<'de, Value = HashMap<usize, BranchId>>
+    where
+        T: Deserialize<'de>,
+    {
+        self.node_list_seed_with(PhantomData::<T>, parent)
+    }
+
+    /// Creates a new deserialization seed for a _single_ node of the node list format,
+    /// using `Seed` for deserializing payloads.
+    ///
+    /// This is useful when the nodes are not stored as one sequence (for example, one node
+    /// per line). `ids` is the mapping being built up: the node's parent is looked up in
+    /// it, and the node itself is added to it. Nodes with a `null` parent are added as
+    /// children of `parent`. The seed returns the node's new branch ID.
+    ///
+    /// The node is only added to [`fire`] once it has been fully deserialized, so a failed
+    /// deserialization leaves both `fire` and `ids` untouched.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` is not an [existing](ForestFire::exists) branch of [`fire`].
+    ///
+    /// [`fire`]: #structfield.fire
+    pub fn node_seed_with<'de, 'a, Seed: DeserializeSeed<'de, Value = T>>(
+        &'a mut self,
+        seed: Seed,
+        parent: BranchId,
+        ids: &'a mut HashMap<usize, BranchId>,
+    ) -> impl DeserializeSeed<'de, Value = BranchId> {
+        if !self.fire.exists(parent) {
+            parent.indexing_panic()
+        }
+
+        KeyedNodeSeed(NodeSeed {
+            fire: &mut self.fire,
+            sub: seed,
+            parent,
+            ids: NodeIds::Keyed(ids),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Creates a new deserialization seed for a _single_ node of the node list format.
+    ///
+    /// See [node_seed_with] for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` is not an [existing](ForestFire::exists) branch of [`fire`].
+    ///
+    /// [node_seed_with]: #method.node_seed_with
+    /// [`fire`]: #structfield.fire
+    pub fn node_seed<'de, 'a>(
+        &'a mut self,
+        parent: BranchId,
+        ids: &'a mut HashMap<usize, BranchId>,
+    ) -> impl DeserializeSeed<'de, Value = BranchId>
+    where
+        T: Deserialize<'de>,
+    {
+        self.node_seed_with(PhantomData::<T>, parent, ids)
+    }
+
+    /// Moves the contents of `self.ashes.ashes` into `self.fire`, in pre-order.
+    fn rekindle(&mut self, parent: BranchId) -> Vec<BranchId> {
+        let ashes = &mut self.ashes.ashes;
+        let children: Vec<Range<usize>> = ashes
+            .nodes
+            .iter()
+            .map(|node| Range::clone(&node.children))
+            .collect();
+        let mut payloads: Vec<Option<T>> = ashes
+            .nodes
+            .drain(..)
+            .map(|node| Some(node.payload))
+            .collect();
+
+        let mut mapping = Vec::with_capacity(payloads.len());
+        self.tree_stack.clear();
+        // the stack is popped from the back, so children are pushed in reverse
+        self.tree_stack.extend(
+            Range::clone(&ashes.root_children)
+                .rev()
+                .map(|i| (i, parent)),
+        );
+        ashes.clear();
+
+        while let Some((idx, parent)) = self.tree_stack.pop() {
+            let payload = payloads[idx]
+                .take()
+                .expect("each node should only be visited once");
+            let branch = self.fire.branch(parent, payload);
+            mapping.push(branch);
+            self.tree_stack
+                .extend(Range::clone(&children[idx]).rev().map(|i| (i, branch)));
+        }
+
+        mapping
+    }
+}
+
+impl<T> Default for FireDeserStorage<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct NativeSeed<'de, 'a, T, Sub: DeserializeSeed<'de, Value = T> + Clone> {
+    storage: &'a mut FireDeserStorage<T>,
+    sub: Sub,
+    phantom: PhantomData<&'de ()>,
+}
+
+impl<'de, 'a, T, Sub: DeserializeSeed<'de, Value = T> + Clone> DeserializeSeed<'de>
+    for NativeSeed<'de, 'a, T, Sub>
+{
+    type Value = Vec<BranchId>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'a, T, Sub: DeserializeSeed<'de, Value = T> + Clone> Visitor<'de>
+    for NativeSeed<'de, 'a, T, Sub>
+{
+    type Value = Vec<BranchId>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a sequence of nodes")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let base = self.storage.fire.next_id().value();
+        let mut mapping = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        loop {
+            let node = NodeSeed {
+                fire: &mut self.storage.fire,
+                sub: self.sub.clone(),
+                parent: BranchId::ROOT,
+                ids: NodeIds::Positional { base },
+                phantom: PhantomData,
+            };
+            match seq.next_element_seed(node)? {
+                Some(Some(branch)) => mapping.push(branch),
+                // the `next_id` entry
+                Some(None) => {}
+                None => break,
+            }
+        }
+        Ok(mapping)
+    }
+}
+
+struct TreeSeed<'de, 'a, T, Sub: DeserializeSeed<'de, Value = T> + Clone> {
+    storage: &'a mut FireDeserStorage<T>,
+    sub: Sub,
+    parent: BranchId,
+    phantom: PhantomData<&'de ()>,
+}
+
+impl<'de, 'a, T, Sub: DeserializeSeed<'de, Value = T> + Clone> DeserializeSeed<'de>
+    for TreeSeed<'de, 'a, T, Sub>
+{
+    type Value = Vec<BranchId>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.storage
+            .ashes
+            .seed_with(self.sub)
+            .deserialize(deserializer)?;
+        Ok(self.storage.rekindle(self.parent))
+    }
+}
+
+struct NodeListSeed<'de, 'a, T, Sub: DeserializeSeed<'de, Value = T> + Clone> {
+    storage: &'a mut FireDeserStorage<T>,
+    sub: Sub,
+    parent: BranchId,
+    phantom: PhantomData<&'de ()>,
+}
+
+impl<'de, 'a, T, Sub: DeserializeSeed<'de, Value = T> + Clone> DeserializeSeed<'de>
+    for NodeListSeed<'de, 'a, T, Sub>
+{
+    type Value = HashMap<usize, BranchId>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'a, T, Sub: DeserializeSeed<'de, Value = T> + Clone> Visitor<'de>
+    for NodeListSeed<'de, 'a, T, Sub>
+{
+    type Value = HashMap<usize, BranchId>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a sequence of nodes")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut ids = HashMap::with_capacity(seq.size_hint().unwrap_or(0));
+        loop {
+            let node = NodeSeed {
+                fire: &mut self.storage.fire,
+                sub: self.sub.clone(),
+                parent: self.parent,
+                ids: NodeIds::Keyed(&mut ids),
+                phantom: PhantomData,
+            };
+            if seq.next_element_seed(node)?.is_none() {
+                break;
+            }
+        }
+        Ok(ids)
+    }
+}
+
+enum NodeIds<'a> {
+    /// The node list format: nodes carry their own `id`s, which are mapped here.
+    Keyed(&'a mut HashMap<usize, BranchId>),
+    /// The native format: a node's ID is its position, or its `id` if nodes were removed,
+    /// and it is placed at `base + id`.
+    Positional { base: usize },
+}
+
+/// A [`NodeSeed`] for the node list format, which never returns `None`.
+struct KeyedNodeSeed<'de, 'a, T, Sub: DeserializeSeed<'de, Value = T>>(NodeSeed<'de, 'a, T, Sub>);
+
+impl<'de, 'a, T, Sub: DeserializeSeed<'de, Value = T>> DeserializeSeed<'de>
+    for KeyedNodeSeed<'de, 'a, T, Sub>
+{
+    type Value = BranchId;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let branch = self.0.deserialize(deserializer)?;
+        Ok(branch.expect("only the native format has `next_id` entries"))
+    }
+}
+
+struct NodeSeed<'de, 'a, T, Sub: DeserializeSeed<'de, Value = T>> {
+    fire: &'a mut ForestFire<T>,
+    sub: Sub,
+    // the parent of nodes which have a `null` parent
+    parent: BranchId,
+    ids: NodeIds<'a>,
+    phantom: PhantomData<&'de ()>,
+}
+
+// returns `None` for the `next_id` entry of the native format
+impl<'de, 'a, T, Sub: DeserializeSeed<'de, Value = T>> DeserializeSeed<'de>
+    for NodeSeed<'de, 'a, T, Sub>
+{
+    type Value = Option<BranchId>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'a, T, Sub: DeserializeSeed<'de, Value = T>> Visitor<'de> for NodeSeed<'de, 'a, T, Sub> {
+    type Value = Option<BranchId>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a map")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        const FIELDS: &[&str] = &["id", "parent", "v", "next_id"];
+
+        #[derive(Debug, Clone, Copy)]
+        enum Key {
+            Id,
+            Parent,
+            Payload,
+            NextId,
+        }
+        struct KeyVisitor;
+        impl<'de> Visitor<'de> for KeyVisitor {
+            type Value = Key;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(formatter, "one of 'id', 'parent', 'v' or 'next_id'")
+            }
+
+            fn visit_str<E>(self, str: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                match str {
+                    "id" => Ok(Key::Id),
+                    "parent" => Ok(Key::Parent),
+                    "v" => Ok(Key::Payload),
+                    "next_id" => Ok(Key::NextId),
+                    _ => Err(E::unknown_field(str, FIELDS)),
+                }
+            }
+        }
+        impl<'de> Deserialize<'de> for Key {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                deserializer.deserialize_str(KeyVisitor)
+            }
+        }
+
+        let keyed = matches!(self.ids, NodeIds::Keyed(_));
+        let mut sub = Some(self.sub);
+        let mut id = None;
+        let mut parent = None;
+        let mut payload = None;
+        let mut next_id = None;
+        while let Some(key) = map.next_key::<Key>()? {
+            match key {
+                Key::NextId if keyed => {
+                    return Err(A::Error::unknown_field("next_id", &FIELDS[..3]));
+                }
+                Key::NextId => {
+                    if next_id.is_some() {
+                        return Err(A::Error::duplicate_field("next_id"));
+                    }
+                    next_id = Some(map.next_value::<usize>()?);
+                }
+                Key::Id => {
+                    if id.is_some() {
+                        return Err(A::Error::duplicate_field("id"));
+                    }
+                    id = Some(map.next_value::<usize>()?);
+                }
+                Key::Parent => {
+                    if parent.is_some() {
+                        return Err(A::Error::duplicate_field("parent"));
+                    }
+                    parent = Some(map.next_value::<Option<usize>>()?);
+                }
+                Key::Payload => {
+                    let Some(sub) = sub.take() else {
+                        return Err(A::Error::duplicate_field("v"));
+                    };
+                    payload = Some(map.next_value_seed(sub)?);
+                }
+            }
+        }
+
+        if let Some(next_id) = next_id {
+            if id.is_some() || parent.is_some() || payload.is_some() {
+                return Err(A::Error::custom(
+                    "`next_id` must be the only key of its entry",
+                ));
+            }
+            let NodeIds::Positional { base } = self.ids else {
+                unreachable!("`next_id` is rejected for the node list format")
+            };
+            skip_to::<T, A::Error>(self.fire, base, next_id)?;
+            return Ok(None);
+        }
+
+        let parent = parent.ok_or_else(|| A::Error::missing_field("parent"))?;
+        let payload = payload.ok_or_else(|| A::Error::missing_field("v"))?;
+
+        match self.ids {
+            NodeIds::Keyed(ids) => {
+                let id = id.ok_or_else(|| A::Error::missing_field("id"))?;
+                let parent = match parent {
+                    None => self.parent,
+                    Some(parent) => *ids.get(&parent).ok_or_else(|| {
+                        A::Error::invalid_value(
+                            Unexpected::Unsigned(parent as u64),
+                            &"the ID of a node which appeared before this one",
+                        )
+                    })?,
+                };
+                if ids.contains_key(&id) {
+                    return Err(A::Error::custom(format_args!(
+                        "node ID {id} appears more than once"
+                    )));
+                }
+
+                let branch = self.fire.branch(parent, payload);
+                ids.insert(id, branch);
+                Ok(Some(branch))
+            }
+            NodeIds::Positional { base } => {
+                let parent = match parent {
+                    None => self.parent,
+                    // parents always come before their children, so they must already
+                    // have been added
+                    Some(parent)
+                        if parent < self.fire.next_id().value() - base
+                            && self.fire.exists(BranchId::new_branch(base + parent)) =>
+                    {
+                        BranchId::new_branch(base + parent)
+                    }
+                    Some(parent) => {
+                        return Err(A::Error::invalid_value(
+                            Unexpected::Unsigned(parent as u64),
+                            &"the branch ID of a node which appeared before this one",
+                        ));
+                    }
+                };
+                if let Some(id) = id {
+                    skip_to::<T, A::Error>(self.fire, base, id)?;
+                }
+                Ok(Some(self.fire.branch(parent, payload)))
+            }
+        }
+    }
+}
+
+/// Skips the branch IDs of `fire` up to `base + id`, which were removed before serializing.
+fn skip_to<T, E: de::Error>(fire: &mut ForestFire<T>, base: usize, id: usize) -> Result<(), E> {
+    let next_id = fire.next_id().value();
+    match base.checked_add(id).and_then(|id| id.checked_sub(next_id)) {
+        Some(skipped) => {
+            fire.skip_ids(skipped);
+            Ok(())
+        }
+        None => Err(E::invalid_value(
+            Unexpected::Unsigned(id as u64),
+            &format!("a branch ID of at least {}", next_id - base).as_str(),
+        )),
+    }
+}
@@ -5,6 +5,8 @@
 //!     - Can be ["burned"](fire::ForestFire::burn) into the immutable [`Ashes`] where it can be traversed as a tree.
 //!     - Nodes can be added to any part of the tree at any time.
 //!     - Each node contains a generic payload which you specify.
+//!     - Can be [de/serialized](fire::serde) without burning it, or loaded from a saved tree
+//!       and added to further.
 //! - [`Ashes`] is the immutable version of the tree:
 //!     - While the tree structure is immutable, the payloads are fully available mutably.
 //!     - Children maintain insertion order.
@@ -91,12 +91,61 @@ fn convoluted() {
     assert_convoluted(&ashes);
 }
 
+#[test]
+fn remove_subtrees() {
+    let mut fire = make_convoluted();
+    let [x, xx, y, yx] = [0, 1, 2, 3].map(fire::BranchId::new);
+    let removed = fire.remove_subtrees(&[xx, yx]);
+    assert_eq!(
+        removed,
+        vec![
+            (xx, 1),
+            (yx, 3),
+            (fire::BranchId::new(4), 4),
+            (fire::BranchId::new(6), 6)
+        ]
+    );
+    assert_eq!(fire.node_count(), 3);
+    assert!(fire.exists(x) && fire.exists(y));
+    assert!(!fire.exists(xx) && fire.was_removed(xx));
+    assert!(!fire.was_removed(x) && !fire.was_removed(fire::BranchId::new(7)));
+
+    // new nodes get fresh IDs and keep their insertion order
+    let z = fire.branch(fire::BranchId::ROOT, 7);
+    assert_eq!(z.value(), 7);
+    fire.branch(x, 8);
+    assert_eq!(*fire.payload(y), 2);
+    assert_eq!(fire.parent(z), Some(fire::BranchId::ROOT));
+    assert_eq!(
+        fire.payloads_mut()
+            .map(|(id, &mut v)| (id.value(), v))
+            .collect::<Vec<_>>(),
+        vec![(0, 0), (2, 2), (5, 5), (7, 7), (8, 8)]
+    );
+
+    let ashes = fire.burn();
+    println!("{}", ashes.print_tree_display());
+    let root = ashes.root();
+    let x = ashes.branch(root.child(0));
+    assert_eq!(x.payload(), Some(&0));
+    assert_eq!(x.fire_id(), Some(fire::BranchId::new(0)));
+    let xy = ashes.branch(x.child(0));
+    assert_eq!(xy.payload(), Some(&5));
+    assert_eq!(xy.fire_id(), Some(fire::BranchId::new(5)));
+    assert_eq!(ashes.branch(x.child(1)).payload(), Some(&8));
+    assert_eq!(ashes.branch(root.child(1)).n_children(), 0);
+    assert_eq!(ashes.branch(root.child(2)).payload(), Some(&7));
+    assert_eq!(root.n_children(), 3);
+}
+
 #[cfg(feature = "serde")]
 mod serde {
+    use ::serde::de::DeserializeSeed;
     use serde_json::json;
 
     use crate::{
         ashes::{Ashes, BranchId},
+        fire::{self, serde::FireDeserStorage},
         test::{assert_convoluted, make_convoluted},
     };
 
@@ -204,4 +253,303 @@ mod serde {
         println!("deserialized {ashes:#?}");
         assert_convoluted(&ashes);
     }
+
+    #[test]
+    fn json_de_fire_tree() {
+        let value = serde_json::to_value(make_convoluted().burn()).unwrap();
+
+        // load the tree below an existing node
+        let mut fire = fire::ForestFire::new();
+        let base = fire.branch(fire::BranchId::ROOT, 100);
+        let mut storage = FireDeserStorage::from_fire(fire);
+        let ids = storage.tree_seed(base).deserialize(&value).unwrap();
+
+        // pre-order: x, xx, xxx, xy, y, yx, yxx
+        let payloads: Vec<u32> = ids.iter().map(|&id| *storage.fire.payload(id)).collect();
+        assert_eq!(payloads, [0, 1, 6, 5, 2, 3, 4]);
+        assert_eq!(storage.fire.parent(ids[0]), Some(base));
+        assert_eq!(storage.fire.parent(ids[2]), Some(ids[1]));
+
+        let ashes = storage.fire.burn();
+        let base = ashes.branch(ashes.root().child(0));
+        assert_eq!(base.payload(), Some(&100));
+        assert_eq!(
+            serde_json::to_value(&ashes).unwrap(),
+            json!({ "0": { "v": 100, "0": value["0"], "1": value["1"] } })
+        );
+    }
+
+    #[test]
+    fn json_de_fire_node_list() {
+        let mut storage = FireDeserStorage::<u32>::new();
+        let ids = storage
+            .node_list_seed(fire::BranchId::ROOT)
+            .deserialize(json!([
+                { "id": 0, "parent": null, "v": 0 },
+                { "id": 1, "parent": 0, "v": 1 },
+                { "v": 2, "id": 2, "parent": null },
+                { "id": 3, "parent": 2, "v": 3 },
+                { "id": 4, "parent": 3, "v": 4 },
+                { "id": 5, "parent": 0, "v": 5 },
+                { "parent": 1, "id": 6, "v": 6 },
+            ]))
+            .unwrap();
+        assert_eq!(ids.len(), 7);
+        assert_eq!(*storage.fire.payload(ids[&6]), 6);
+        assert_convoluted(&storage.fire.burn());
+
+        // parents must come first
+        let mut storage = FireDeserStorage::<u32>::new();
+        assert!(
+            storage
+                .node_list_seed(fire::BranchId::ROOT)
+                .deserialize(json!([
+                    { "id": 1, "parent": 0, "v": 1 },
+                    { "id": 0, "parent": null, "v": 0 },
+                ]))
+                .is_err()
+        );
+        // IDs must be unique
+        let mut storage = FireDeserStorage::<u32>::new();
+        assert!(
+            storage
+                .node_list_seed(fire::BranchId::ROOT)
+                .deserialize(json!([
+                    { "id": 0, "parent": null, "v": 1 },
+                    { "id": 0, "parent": null, "v": 0 },
+                ]))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn json_fire_ser_and_de() {
+        let fire = make_convoluted();
+        let value = serde_json::to_value(&fire).unwrap();
+        println!("serialized {value:#}");
+        assert_eq!(
+            value,
+            json!([
+                { "parent": null, "v": 0 },
+                { "parent": 0, "v": 1 },
+                { "parent": null, "v": 2 },
+                { "parent": 2, "v": 3 },
+                { "parent": 3, "v": 4 },
+                { "parent": 0, "v": 5 },
+                { "parent": 1, "v": 6 },
+            ])
+        );
+
+        let mut de: fire::ForestFire<u32> = serde_json::from_value(value.clone()).unwrap();
+        // branch IDs are kept, so we can keep recording into the same nodes
+        for i in 0..fire.node_count() {
+            let id = fire::BranchId::new(i);
+            assert_eq!(de.payload(id), fire.payload(id));
+            assert_eq!(de.parent(id), fire.parent(id));
+        }
+        assert_eq!(de.next_id(), fire.next_id());
+        de.branch(fire::BranchId::new(4), 7);
+        let ashes = de.burn();
+        let yxx = ashes.branch(
+            ashes
+                .branch(ashes.branch(ashes.root().child(1)).child(0))
+                .child(0),
+        );
+        assert_eq!(ashes.branch(yxx.child(0)).payload(), Some(&7));
+
+        // appending to a non-empty tree offsets the IDs
+        let mut storage = FireDeserStorage::from_fire(make_convoluted());
+        let ids = storage.seed().deserialize(&value).unwrap();
+        assert_eq!(ids[0], fire::BranchId::new(7));
+        assert_eq!(storage.fire.parent(ids[6]), Some(ids[1]));
+
+        // parents must come before their children
+        assert!(
+            serde_json::from_value::<fire::ForestFire<u32>>(json!([
+                { "parent": 1, "v": 0 },
+                { "parent": null, "v": 1 },
+            ]))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn json_ser_ctx() {
+        use std::collections::BTreeMap;
+
+        let ashes = make_convoluted().burn();
+        let value = serde_json::to_value(ashes.serializable_with_ctx(|ctx| {
+            let mut map = BTreeMap::new();
+            map.insert("v", *ctx.payload as usize);
+            map.insert("depth", ctx.depth);
+            map.insert("children", ctx.branch.n_children());
+            map
+        }))
+        .unwrap();
+        println!("serialized {value:#}");
+        assert_eq!(value["0"]["v"], 0);
+        assert_eq!(value["0"]["depth"], 1);
+        assert_eq!(value["0"]["children"], 2);
+        assert_eq!(value["0"]["0"]["0"]["v"], 6);
+        assert_eq!(value["0"]["0"]["0"]["depth"], 3);
+        assert_eq!(value["1"]["0"]["children"], 1);
+
+        // anything other than a map or struct can't be placed next to the children
+        assert!(serde_json::to_value(ashes.serializable_with_ctx(|ctx| *ctx.payload)).is_err());
+
+        // the extra keys are skipped when deserializing
+        let ashes: Ashes<u32> = serde_json::from_value(value).unwrap();
+        assert_convoluted(&ashes);
+        // ...but keys which look like children are not
+        assert!(serde_json::from_value::<Ashes<u32>>(json!({ "0": { "v": 0 }, "1x": 0 })).is_err());
+    }
+
+    #[test]
+    fn native_after_removal() {
+        let mut fire = make_convoluted();
+        // xx and xxx, which is the last node
+        fire.remove_subtrees(&[fire::BranchId::new(1)]);
+        let json = serde_json::to_value(&fire).unwrap();
+        println!("serialized {json:#}");
+        assert_eq!(json[3], json!({ "id": 4, "parent": 3, "v": 4 }));
+        assert_eq!(json[5], json!({ "next_id": 7 }));
+
+        let mut de: fire::ForestFire<u32> = serde_json::from_value(json.clone()).unwrap();
+        // branch IDs are kept, and removed ones stay removed
+        for i in [0, 2, 3, 4, 5] {
+            let id = fire::BranchId::new(i);
+            assert_eq!(de.payload(id), fire.payload(id));
+            assert_eq!(de.parent(id), fire.parent(id));
+        }
+        assert!(de.was_removed(fire::BranchId::new(1)));
+        assert!(de.was_removed(fire::BranchId::new(6)));
+        assert_eq!(de.next_id(), fire.next_id());
+        let yxx = fire::BranchId::new(4);
+        assert_eq!(de.branch(yxx, 7), fire.branch(yxx, 7));
+        assert_eq!(
+            de.burn().print_tree_display().to_string(),
+            fire.burn().print_tree_display().to_string()
+        );
+
+        // appending to a non-empty tree offsets the IDs, along with the removed ones
+        let mut storage = FireDeserStorage::from_fire(make_convoluted());
+        let ids = storage.seed().deserialize(&json).unwrap();
+        assert_eq!(ids, [7, 9, 10, 11, 12].map(fire::BranchId::new));
+        assert!(storage.fire.was_removed(fire::BranchId::new(8)));
+        assert_eq!(storage.fire.next_id(), fire::BranchId::new(14));
+
+        // IDs must increase
+        assert!(
+            serde_json::from_value::<fire::ForestFire<u32>>(json!([
+                { "id": 1, "parent": null, "v": 0 },
+                { "id": 0, "parent": null, "v": 1 },
+            ]))
+            .is_err()
+        );
+        // removed nodes can't be parents
+        assert!(
+            serde_json::from_value::<fire::ForestFire<u32>>(json!([
+                { "id": 1, "parent": 0, "v": 0 },
+            ]))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn fire_id() {
+        let fire = make_convoluted();
+        let ashes = fire.clone().burn();
+        assert_eq!(ashes.root().fire_id(), None);
+
+        let mut stack = vec![ashes.root()];
+        while let Some(branch) = stack.pop() {
+            for child in branch.child_iter() {
+                let child = ashes.branch(child);
+                let id = child.fire_id().unwrap();
+                assert_eq!(fire.payload(id), child.payload().unwrap());
+                stack.push(child);
+            }
+        }
+
+        let de: Ashes<u32> = serde_json::from_value(serde_json::to_value(&ashes).unwrap()).unwrap();
+        assert_eq!(de.branch(de.root().child(0)).fire_id(), None);
+    }
+
+    #[test]
+    fn json_ser_opts() {
+        use crate::ashes::serde::{ChildCap, SerOptions};
+
+        let mut fire = make_convoluted();
+        // give `y` some more children to cap
+        let y = fire::BranchId::new(2);
+        for i in 10..15 {
+            fire.branch(y, i);
+        }
+        let ashes = fire.burn();
+        let ids =
+            |id| -> Vec<usize> { ashes.branch(id).child_iter().map(|id| id.value()).collect() };
+        let x = ashes.root().child(0);
+        let y = ashes.root().child(1);
+        let yx = ashes.branch(y).child(0);
+
+        let mut opts = SerOptions::new();
+        opts.max_depth = Some(1);
+        let value = serde_json::to_value(ashes.serializable_with_opts(opts, |v| v)).unwrap();
+        println!("serialized {value:#}");
+        assert_eq!(
+            value,
+            json!({
+                "0": {
+                    "v": 0,
+                    "0": { "omitted": { "nodes": 3, "ids": ids(x) } }
+                },
+                "1": {
+                    "v": 2,
+                    "0": { "omitted": { "nodes": 7, "ids": ids(y) } }
+                }
+            })
+        );
+
+        let mut opts = SerOptions::new();
+        opts.child_cap = Some(ChildCap { first: 1, last: 2 });
+        opts.start = y;
+        let value = serde_json::to_value(ashes.serializable_with_opts(opts, |v| v)).unwrap();
+        println!("serialized {value:#}");
+        assert_eq!(
+            value,
+            json!({
+                "0": {
+                    "v": 2,
+                    "0": { "v": 3, "0": { "v": 4 } },
+                    "1": { "omitted": { "nodes": 3, "ids": ids(y)[1..4] } },
+                    "4": { "v": 13 },
+                    "5": { "v": 14 }
+                }
+            })
+        );
+
+        // the omitted IDs can be used to fetch the rest later
+        let full = serde_json::to_value(&ashes).unwrap();
+        let omitted = value["0"]["1"]["omitted"]["ids"].as_array().unwrap();
+        for (i, id) in omitted.iter().enumerate() {
+            let mut opts = SerOptions::new();
+            opts.start = BranchId::new(id.as_u64().unwrap() as usize);
+            let value = serde_json::to_value(ashes.serializable_with_opts(opts, |v| v)).unwrap();
+            assert_eq!(value, json!({ "0": full["1"][(i + 1).to_string()] }));
+        }
+
+        // with a max depth of 0, even the start node is left out
+        let mut opts = SerOptions::new();
+        opts.start = yx;
+        opts.max_depth = Some(0);
+        let value = serde_json::to_value(ashes.serializable_with_opts(opts, |v| v)).unwrap();
+        assert_eq!(
+            value,
+            json!({ "0": { "omitted": { "nodes": 2, "ids": [yx.value()] } } })
+        );
+
+        // marker nodes have no payload, so partial output can't be read back as a tree
+        let err = serde_json::from_value::<Ashes<i32>>(value).unwrap_err();
+        assert!(err.to_string().contains("missing field `v`"), "{err}");
+    }
 }
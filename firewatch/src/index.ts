@@ -1,18 +1,49 @@
 import { title } from "./fireAnim";
-import { buildChild, elById, nn, removeFromParent, TODO } from "./util";
+import { buildChild, elById, nn, removeFromParent } from "./util";
 import demo from "./demo.json";
 
 interface Payload {
+    id?: unknown;
     level?: unknown;
     name?: unknown;
+    target?: unknown;
     ctx?: any;
     file?: unknown;
     is_span?: unknown;
+    // whether a panic passed through the span
+    unwound?: unknown;
     line?: unknown;
+    time?: Time;
+    thread?: Thread;
+}
+
+interface Thread {
+    id?: unknown;
+    name?: unknown;
+}
+
+// all in nanoseconds
+interface Time {
+    created?: unknown;
+    busy?: unknown;
+    idle?: unknown;
+    // not a time, but how many times a span was entered
+    entries?: unknown;
+}
+
+interface Omitted {
+    nodes?: unknown;
+    ids?: unknown;
 }
 
 interface Tree {
     v?: Payload,
+    // marker for nodes left out during serialization
+    omitted?: Omitted,
+    // number of nodes dropped here because the capture budget ran out
+    dropped?: unknown,
+    // only on root: span id -> ids of the spans it follows from
+    follows_from?: Record<string, unknown>,
     [child: number]: Tree;
 }
 
@@ -40,7 +71,42 @@ interface NodeKv {
     value: string;
 }
 
-function prepareNodeMap(payload: Payload | undefined): NodeKv[] {
+function formatNanos(nanos: number): string {
+    if (nanos < 1e3)
+        return `${nanos}ns`;
+    if (nanos < 1e6)
+        return `${(nanos / 1e3).toFixed(1)}µs`;
+    if (nanos < 1e9)
+        return `${(nanos / 1e6).toFixed(1)}ms`;
+    return `${(nanos / 1e9).toFixed(2)}s`;
+}
+
+// cross-links between nodes, gathered from the whole tree before building it
+class Links {
+    names: Map<unknown, string> = new Map();
+    followsFrom: Record<string, unknown> = {};
+
+    constructor(tree: Tree) {
+        if (tree.follows_from != undefined)
+            this.followsFrom = tree.follows_from;
+        const stack = [tree];
+        let next: Tree | undefined;
+        while ((next = stack.pop()) != undefined) {
+            if (next.v?.id != undefined)
+                this.names.set(next.v.id, String(next.v.name));
+            for (const [key, child] of Object.entries(next))
+                if (!Number.isNaN(Number.parseInt(key, 10)))
+                    stack.push(child);
+        }
+    }
+
+    describe(id: unknown): string {
+        const name = this.names.get(id);
+        return name == undefined ? `#${id}` : `${name} (#${id})`;
+    }
+}
+
+function prepareNodeMap(payload: Payload | undefined, links: Links): NodeKv[] {
     const kvs: NodeKv[] = [];
     if (payload == undefined)
         return kvs;
@@ -51,6 +117,31 @@ function prepareNodeMap(payload: Payload | undefined): NodeKv[] {
             value: `${payload.file}:${payload.line}`
         });
 
+    const thread = payload.thread;
+    if (thread != undefined)
+        kvs.push({
+            name: "thread",
+            special: true,
+            value: thread.name == null ? `#${thread.id}` : `${thread.name} (#${thread.id})`
+        });
+
+    const time = payload.time;
+    if (time != undefined && typeof (time.busy) == "number" && typeof (time.idle) == "number")
+        kvs.push({
+            name: "time",
+            special: true,
+            value: `busy ${formatNanos(time.busy)}, idle ${formatNanos(time.idle)}`
+                + (typeof (time.entries) == "number" ? `, entered ${time.entries}x` : "")
+        });
+
+    const follows = payload.id == undefined ? undefined : links.followsFrom[String(payload.id)];
+    if (Array.isArray(follows))
+        kvs.push({
+            name: "follows from",
+            special: true,
+            value: follows.map(id => links.describe(id)).join(", ")
+        });
+
     const ctx = payload.ctx;
     if (ctx != undefined)
         for (const [key, value] of Object.entries(ctx)) {
@@ -60,7 +151,7 @@ function prepareNodeMap(payload: Payload | undefined): NodeKv[] {
 
             kvs.push({
                 name: key,
-                value: typeof (value) == "string" ? value : JSON.stringify(value),
+                value: formatField(value),
                 special: false
             });
         }
@@ -68,6 +159,15 @@ function prepareNodeMap(payload: Payload | undefined): NodeKv[] {
     return kvs;
 }
 
+function formatField(value: any): string {
+    if (typeof (value) == "string")
+        return value;
+    // error chains are arrays of strings, shown like `error: source: source of source`
+    if (Array.isArray(value) && value.length > 0 && value.every(v => typeof (v) == "string"))
+        return value.join(": ");
+    return JSON.stringify(value);
+}
+
 function buildHeader(parent: HTMLElement, stats: TreeStats, innerElement: HTMLElement, payload: Payload, isMapEmpty: boolean) {
     stats.buildChild(parent, "span", span => {
         span.classList.add("payload-toggle-collapsed");
@@ -105,6 +205,20 @@ function buildHeader(parent: HTMLElement, stats: TreeStats, innerElement: HTMLEl
         // info.classList.add("payload-info-" + levelLo);
         // parent.classList.add("tree-node-" + level.toLowerCase());
     }
+    // recorded by `forrust_fire_tracing::panic::PanicHook`
+    if (payload.is_span === false && payload.target === "panic") {
+        stats.buildChild(parent, "span", el => {
+            el.textContent = "PANIC ";
+            el.classList.add("payload-panic");
+        });
+    }
+    if (payload.unwound === true) {
+        stats.buildChild(parent, "span", el => {
+            el.textContent = "UNWOUND ";
+            el.title = "a panic passed through this span";
+            el.classList.add("payload-unwound");
+        });
+    }
     const putName = (name: any) => stats.buildChild(parent, "span", el => {
         el.textContent = String(name);
         el.classList.add("payload-name");
@@ -139,14 +253,25 @@ function buildNodeMap(parent: HTMLElement, stats: TreeStats, map: NodeKv[]) {
     parent.appendChild(div);
 }
 
-function buildTree(parent: HTMLElement, stats: TreeStats, tree: Tree, level: number) {
-    const map = prepareNodeMap(tree.v);
+function buildTree(parent: HTMLElement, stats: TreeStats, links: Links, tree: Tree, level: number) {
+    const map = prepareNodeMap(tree.v, links);
     const payload = tree.v;
 
     parent.classList.add("tree-node-" + level % 2);
     let children: (HTMLElement | null)[] = [];
 
     const inner = stats.buildEl("div");
+    if (tree.omitted != null) {
+        stats.buildChild(parent, "p", p => {
+            p.classList.add("payload-info");
+            stats.buildChild(p, "span", el => {
+                el.textContent = `(${tree.omitted?.nodes} nodes omitted)`;
+                el.classList.add("payload-omitted");
+                if (Array.isArray(tree.omitted?.ids))
+                    el.title = "ids: " + tree.omitted.ids.join(", ");
+            });
+        });
+    }
     if (payload != null) {
         stats.buildChild(parent, "p", p => {
             p.classList.add("payload-info");
@@ -162,15 +287,25 @@ function buildTree(parent: HTMLElement, stats: TreeStats, tree: Tree, level: num
 
     buildNodeMap(inner, stats, map);
 
+    // dropped nodes were lost inside of this one, so show them with its contents
+    if (typeof (tree.dropped) == "number") {
+        const dropped = tree.dropped;
+        stats.buildChild(inner, "p", p => {
+            p.classList.add("payload-info");
+            stats.buildChild(p, "span", el => {
+                el.textContent = `(${dropped} nodes dropped, over budget)`;
+                el.classList.add("payload-omitted");
+            });
+        });
+    }
+
     for (const [key, v] of Object.entries(tree)) {
-        if (key == "v") {
+        const iKey = Number.parseInt(key, 10);
+        // anything other than a child (payload, markers, links) is handled above
+        if (Number.isNaN(iKey)) {
             continue;
         } else {
             const value: any = v;
-            const iKey = Number.parseInt(key, 10);
-            if (Number.isNaN(iKey)) {
-                TODO("handle unexpected keys: " + key);
-            }
             while (children.length <= iKey) {
                 children.push(null);
             }
@@ -179,19 +314,18 @@ function buildTree(parent: HTMLElement, stats: TreeStats, tree: Tree, level: num
             // if (typeof value.v?.level == "string")
             //     child.classList.add("tree-node-" + (<string>value.v.level).toLowerCase());
 
-            buildTree(child, stats, value, level + 1);
+            buildTree(child, stats, links, value, level + 1);
 
             children[iKey] = child;
             stats.nodes += 1;
         }
     }
 
-    let i = 0;
     for (const child of children) {
+        // children may be skipped if some of them were omitted
         if (child == null)
-            TODO();
+            continue;
         inner.appendChild(child);
-        i++;
     }
 }
 
@@ -211,7 +345,7 @@ function begin(on: any) {
             removeFromParent(child);
     }
     const stats = new TreeStats();
-    buildTree(treeView, stats, on, 0);
+    buildTree(treeView, stats, new Links(on), on, 0);
 
     console.timeEnd("build tree");
     elById(HTMLElement, "view-header-msg").textContent = `tree nodes: ${stats.nodes}; html nodes: ${stats.htmlNodes}`;
@@ -251,4 +385,9 @@ window.addEventListener("dragover", e => {
 (<any>window).openFile = openFileDialog;
 (<any>window).openDemo = () => {
     begin(demo);
-};
\ No newline at end of file
+};
+
+// reports written by `forrust_fire_tracing::html` carry their trace inside of the page
+const embedded = document.getElementById("embedded-trace");
+if (embedded?.textContent)
+    begin(JSON.parse(embedded.textContent));
@@ -0,0 +1,17 @@
+import { UserConfig } from "vite";
+
+// bundles the viewer into a single script, which `forrust_fire_tracing` embeds into its
+// HTML reports
+export default {
+    root: "./src/",
+    build: {
+        outDir: "../../crates/tracing/firewatch",
+        emptyOutDir: false,
+        minify: false,
+        lib: {
+            entry: "./index.ts",
+            formats: ["es"],
+            fileName: () => "firewatch.js",
+        },
+    },
+} satisfies UserConfig;